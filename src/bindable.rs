@@ -1,13 +1,20 @@
 use std::mem;
 use wgpu::util::DeviceExt;
+use std::future::Future;
 use std::marker::PhantomData;
+use std::pin::Pin;
+use std::task::{Context, Poll};
 use bytemuck::Pod;
+use futures::task::noop_waker_ref;
 
 use crate::{
-    dimensions::Dimensions,
+    dimensions::{Dimensions, Rect},
     directions::{RenderDir, RenderMotion},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use pollster::block_on;
+
 // ---------------------------------------------------------------------
 
 #[derive(Clone, Copy)]
@@ -277,6 +284,30 @@ impl<T> Buffer2D<T> {
     pub fn dim(&self) -> Dimensions {
         self.dim
     }
+
+    // The runs of elements, as (start, length), that make up a rectangle.
+    // The rectangle wraps around at the edges, and is cut down to fit.
+    fn rect_spans(
+        &self,
+        rect: Rect,
+    ) -> Vec<(usize, usize)> {
+        let width = self.dim.width() as usize;
+        let height = self.dim.height() as usize;
+        let x0 = rect.x.rem_euclid(width as i32) as usize;
+        let rw = (rect.width as usize).min(width);
+        let rh = (rect.height as usize).min(height);
+
+        let mut spans = Vec::new();
+        for dy in 0..rh {
+            let y = (rect.y + dy as i32).rem_euclid(height as i32) as usize;
+            let first = rw.min(width - x0);
+            spans.push((y * width + x0, first));
+            if first < rw {
+                spans.push((y * width, rw - first));
+            }
+        }
+        spans
+    }
 }
 
 impl<T> Buffer2D<T> where T: Pod {
@@ -318,6 +349,116 @@ impl<T> Buffer2D<T> where T: Pod {
         );
         queue.submit(Some(command_encoder.finish()));
     }
+
+    // Read the contents of this buffer back from the GPU.
+    // This waits for the GPU to catch up, so it doesn't work under wasm32.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn copyout_vec(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Vec<T> {
+        let size = (self.dim.area() * mem::size_of::<T>()) as u64;
+        let export_buf = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(format!("{} data export buffer", self.label).as_str()),
+            size,
+            usage: wgpu::BufferUsages::MAP_READ
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut command_encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some(format!("exporting {} data", self.label).as_str())
+            });
+        command_encoder.copy_buffer_to_buffer(
+            self.buf(), 0, &export_buf, 0, size);
+        queue.submit(Some(command_encoder.finish()));
+
+        let buffer_slice = export_buf.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+        block_on(buffer_future).expect("failed to read back buffer");
+
+        let data: Vec<T> =
+            bytemuck::cast_slice(&buffer_slice.get_mapped_range()).to_vec();
+        export_buf.unmap();
+        data
+    }
+
+    // Write a rectangle of data, one row after another, into the buffer.
+    pub fn write_rect(
+        &self,
+        queue: &wgpu::Queue,
+        rect: Rect,
+        data: &[T],
+    ) {
+        let mut pos = 0;
+        for (start, len) in self.rect_spans(rect) {
+            queue.write_buffer(self.buf(), (start * mem::size_of::<T>()) as _,
+                bytemuck::cast_slice(&data[pos..(pos + len)]));
+            pos += len;
+        }
+    }
+
+    // Start reading a rectangle of data back from the GPU, one row after
+    // another, by copying it out as part of the given commands.  It can be
+    // taken from the Readback once they've been submitted.
+    pub fn enqueue_read_rect(
+        &self,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+        rect: Rect,
+    ) -> Readback<T> {
+        let spans = self.rect_spans(rect);
+        let count: usize = spans.iter().map(|(_, len)| len).sum();
+
+        // A buffer can't be empty, even if the rectangle is.
+        let elt = mem::size_of::<T>();
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(format!("{} rect export buffer", self.label).as_str()),
+            size: (count.max(1) * elt) as _,
+            usage: wgpu::BufferUsages::MAP_READ
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let mut pos = 0;
+        for (start, len) in spans {
+            if len > 0 {
+                command_encoder.copy_buffer_to_buffer(
+                    self.buf(), (start * elt) as _, &buffer, (pos * elt) as _,
+                    (len * elt) as _);
+            }
+            pos += len;
+        }
+
+        Readback {
+            buffer,
+            count,
+            mapping: None,
+            data: None,
+        }
+    }
+
+    // Read a rectangle of data back from the GPU, one row after another.
+    // Like copyout_vec(), this waits, so it doesn't work under wasm32.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_rect(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rect: Rect,
+    ) -> Vec<T> {
+        let mut command_encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some(format!("exporting {} rect", self.label).as_str())
+            });
+        let mut readback = self.enqueue_read_rect(device, &mut command_encoder, rect);
+        queue.submit(Some(command_encoder.finish()));
+        readback.map();
+        readback.wait(device)
+    }
 }
 
 impl<T> Bindable for Buffer2D<T> {
@@ -332,6 +473,73 @@ impl<T> Bindable for Buffer2D<T> {
 
 // ---------------------------------------------------------------------
 
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>>>>;
+
+// Data on its way back from the GPU, from Buffer2D::enqueue_read_rect().
+// Once the commands that copy it out have been submitted, map() starts
+// reading it, and it can be waited for, or checked on now and then
+// without waiting, so that the GPU doesn't have to be caught up with.
+pub struct Readback<T> {
+    buffer: wgpu::Buffer,
+    count: usize,
+    mapping: Option<MapFuture>,
+    data: Option<Vec<T>>,       // once it's arrived
+}
+
+impl<T> Readback<T> where T: Pod {
+    // The commands that copy the data out have been submitted.
+    pub fn map(&mut self) {
+        self.mapping = Some(Box::pin(
+            self.buffer.slice(..).map_async(wgpu::MapMode::Read)));
+    }
+
+    fn read(&mut self) -> Vec<T> {
+        let size = (self.count * mem::size_of::<T>()) as wgpu::BufferAddress;
+        let data: Vec<T> = bytemuck::cast_slice(
+            &self.buffer.slice(..size).get_mapped_range()).to_vec();
+        self.buffer.unmap();
+        data
+    }
+
+    // Whether the data has arrived, without waiting for it.  Something has
+    // to poll the device for it to get anywhere.
+    pub fn is_ready(&mut self) -> bool {
+        if self.data.is_none() {
+            let mapping = self.mapping.as_mut().expect("readback isn't mapping");
+            let mut cx = Context::from_waker(noop_waker_ref());
+            let poll = mapping.as_mut().poll(&mut cx);
+            if let Poll::Ready(result) = poll {
+                result.expect("failed to read back buffer");
+                self.mapping = None;
+                self.data = Some(self.read());
+            }
+        }
+        self.data.is_some()
+    }
+
+    // The data, once is_ready() has said that it's arrived.
+    pub fn take(&mut self) -> Vec<T> {
+        self.data.take().expect("readback hasn't arrived")
+    }
+
+    // The data, waiting for the GPU if it hasn't arrived yet.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(
+        &mut self,
+        device: &wgpu::Device,
+    ) -> Vec<T> {
+        if let Some(data) = self.data.take() {
+            return data;
+        }
+        let mapping = self.mapping.take().expect("readback isn't mapping");
+        device.poll(wgpu::Maintain::Wait);
+        block_on(mapping).expect("failed to read back buffer");
+        self.read()
+    }
+}
+
+// ---------------------------------------------------------------------
+
 pub struct Texture {
     texture_view: wgpu::TextureView,
     format: wgpu::TextureFormat,
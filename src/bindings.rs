@@ -0,0 +1,313 @@
+// Which keys do what.
+//
+// Every keyboard command is an Action, and a table of Bindings maps chords
+// (a key plus modifiers, like "Ctrl+Shift+Z") onto them.  The defaults can
+// be overridden in the [bindings] section of the config file:
+//
+//     [bindings]
+//     quit = ["Escape", "Ctrl+Q"]
+//     help = "F1"
+//     record = []             # unbound
+//
+// Key names are winit's VirtualKeyCode names, like "A", "Key1", "F1",
+// "Space", "LBracket" or "PageUp".
+
+use serde::{de::IntoDeserializer, Deserialize};
+use std::{collections::BTreeMap, fmt};
+use winit::event::{ModifiersState, VirtualKeyCode};
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    FullScreen,
+    UnFullScreen,
+    Help,
+    Hud,
+    Console,
+    Colormap,
+    GridLines,
+    Trails,
+    AgeColors,
+    Changes,
+    Difference,
+    Terrain,
+    Screenshot,
+    SupersampledScreenshot,
+    Record,
+    ExportGif,
+    SaveSnapshot,
+    LoadSnapshot,
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ResetView,
+    BrushSmaller,
+    BrushBigger,
+    BrushShape,
+    SelectTool,
+    Copy,
+    Cut,
+    Paste,
+    Rotate,
+    FlipX,
+    FlipY,
+    PasteMode,
+    Patterns,
+    Undo,
+    Redo,
+}
+
+impl Action {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::FullScreen => "full screen",
+            Action::UnFullScreen => "leave full screen",
+            Action::Help => "show or hide this help",
+            Action::Hud => "show or hide the HUD",
+            Action::Console => "open the command console",
+            Action::Colormap => "switch to the next colormap",
+            Action::GridLines => "show or hide lines between cells",
+            Action::Trails => "show or hide trails of where cells have been",
+            Action::AgeColors => "color cells by how long they've been alive",
+            Action::Changes => "show or hide newborn and recently dead cells",
+            Action::Difference => "show or hide where compared runs differ",
+            Action::Terrain => "switch between the flat and 3D views",
+            Action::Screenshot => "save a screenshot",
+            Action::SupersampledScreenshot => "save a supersampled screenshot",
+            Action::Record => "start or stop recording",
+            Action::ExportGif => "export an animated GIF",
+            Action::SaveSnapshot => "save a snapshot",
+            Action::LoadSnapshot => "load the snapshot",
+            Action::PanLeft => "pan left",
+            Action::PanRight => "pan right",
+            Action::PanUp => "pan up",
+            Action::PanDown => "pan down",
+            Action::ResetView => "reset the view",
+            Action::BrushSmaller => "smaller brush",
+            Action::BrushBigger => "bigger brush",
+            Action::BrushShape => "change the brush shape",
+            Action::SelectTool => "select with the mouse, or go back to drawing",
+            Action::Copy => "copy the selection",
+            Action::Cut => "cut the selection",
+            Action::Paste => "paste with the mouse",
+            Action::Rotate => "rotate the clipboard",
+            Action::FlipX => "flip the clipboard horizontally",
+            Action::FlipY => "flip the clipboard vertically",
+            Action::PasteMode => "change how pasting combines cells",
+            Action::Patterns => "choose a pattern to stamp",
+            Action::Undo => "undo",
+            Action::Redo => "redo",
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Chord {
+    key: VirtualKeyCode,
+    modifiers: ModifiersState,
+}
+
+impl Chord {
+    pub fn new(
+        key: VirtualKeyCode,
+        modifiers: ModifiersState,
+    ) -> Self {
+        Chord {
+            key,
+            modifiers,
+        }
+    }
+
+    // Parse something like "Ctrl+Shift+Z".
+    pub fn parse(
+        text: &str,
+    ) -> Result<Chord, String> {
+        let mut parts: Vec<&str> = text.split('+').map(str::trim).collect();
+        let key = parts.pop().unwrap_or("");
+        let key = VirtualKeyCode::deserialize(
+            key.into_deserializer())
+            .map_err(|_: serde::de::value::Error|
+                format!("unknown key \"{}\" in \"{}\"", key, text))?;
+
+        let mut modifiers = ModifiersState::empty();
+        for part in parts {
+            modifiers |= match part.to_lowercase().as_str() {
+                "ctrl" | "control" => ModifiersState::CTRL,
+                "shift" => ModifiersState::SHIFT,
+                "alt" | "option" => ModifiersState::ALT,
+                "logo" | "super" | "cmd" | "command" => ModifiersState::LOGO,
+                _ => return Err(format!("unknown modifier \"{}\" in \"{}\"",
+                    part, text)),
+            };
+        }
+
+        Ok(Chord::new(key, modifiers))
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.ctrl() {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt() {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.logo() {
+            write!(f, "Logo+")?;
+        }
+        if self.modifiers.shift() {
+            write!(f, "Shift+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// How chords are written in the config file: one, or a list.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Keys {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Keys {
+    fn names(&self) -> Vec<&str> {
+        match self {
+            Keys::One(name) => vec![name.as_str()],
+            Keys::Many(names) => names.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+const DEFAULT_BINDINGS: &[(&str, Action)] = &[
+    ("Escape", Action::Quit),
+    ("F", Action::FullScreen),
+    ("W", Action::UnFullScreen),
+    ("F1", Action::Help),
+    ("Shift+Slash", Action::Help),
+    ("I", Action::Hud),
+    ("Grave", Action::Console),
+    ("K", Action::Colormap),
+    ("Shift+G", Action::GridLines),
+    ("A", Action::Trails),
+    ("Y", Action::AgeColors),
+    ("Shift+Y", Action::Changes),
+    ("D", Action::Difference),
+    ("Key3", Action::Terrain),
+    ("P", Action::Screenshot),
+    ("O", Action::SupersampledScreenshot),
+    ("R", Action::Record),
+    ("G", Action::ExportGif),
+    ("S", Action::SaveSnapshot),
+    ("L", Action::LoadSnapshot),
+    ("Left", Action::PanLeft),
+    ("Right", Action::PanRight),
+    ("Up", Action::PanUp),
+    ("Down", Action::PanDown),
+    ("Home", Action::ResetView),
+    ("LBracket", Action::BrushSmaller),
+    ("RBracket", Action::BrushBigger),
+    ("B", Action::BrushShape),
+    ("E", Action::SelectTool),
+    ("C", Action::Copy),
+    ("Ctrl+C", Action::Copy),
+    ("X", Action::Cut),
+    ("Ctrl+X", Action::Cut),
+    ("V", Action::Paste),
+    ("Ctrl+V", Action::Paste),
+    ("T", Action::Rotate),
+    ("H", Action::FlipX),
+    ("J", Action::FlipY),
+    ("M", Action::PasteMode),
+    ("N", Action::Patterns),
+    ("Ctrl+Z", Action::Undo),
+    ("Logo+Z", Action::Undo),
+    ("Ctrl+Y", Action::Redo),
+    ("Ctrl+Shift+Z", Action::Redo),
+    ("Logo+Shift+Z", Action::Redo),
+];
+
+pub struct Bindings {
+    table: Vec<(Chord, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Bindings {
+            table: DEFAULT_BINDINGS.iter().map(|&(chord, action)| {
+                (Chord::parse(chord).expect("bad default binding"), action)
+            }).collect(),
+        }
+    }
+}
+
+impl Bindings {
+    // The defaults, with the given actions rebound.  Any action that's
+    // mentioned loses all of its default chords, and any chord that's
+    // mentioned is taken away from whatever action it had.
+    pub fn with_overrides(
+        overrides: &BTreeMap<Action, Keys>,
+    ) -> Self {
+        let mut bindings = Bindings::default();
+        for (&action, keys) in overrides {
+            bindings.table.retain(|&(_, a)| a != action);
+            for name in keys.names() {
+                match Chord::parse(name) {
+                    Ok(chord) => {
+                        bindings.table.retain(|&(c, _)| c != chord);
+                        bindings.table.push((chord, action));
+                    },
+                    Err(e) => log::warn!("bindings: {}", e),
+                }
+            }
+        }
+        bindings
+    }
+
+    // What a key does, given the modifiers that are held down.
+    pub fn lookup(
+        &self,
+        key: VirtualKeyCode,
+        modifiers: ModifiersState,
+    ) -> Option<Action> {
+        let chord = Chord::new(key, modifiers);
+        self.table.iter().find(|&&(c, _)| c == chord).map(|&(_, a)| a)
+    }
+
+    // A line for each action, listing the chords bound to it.
+    pub fn help(&self) -> Vec<String> {
+        let mut actions: BTreeMap<Action, Vec<String>> = BTreeMap::new();
+        for (chord, action) in &self.table {
+            actions.entry(*action).or_default().push(chord.to_string());
+        }
+        actions.iter().map(|(action, chords)| {
+            format!("{:<24} {}", chords.join(", "), action.describe())
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chords() {
+        assert_eq!(Chord::parse("Z"),
+            Ok(Chord::new(VirtualKeyCode::Z, ModifiersState::empty())));
+        assert_eq!(Chord::parse("ctrl + Shift+Z"),
+            Ok(Chord::new(VirtualKeyCode::Z, ModifiersState::CTRL | ModifiersState::SHIFT)));
+        assert_eq!(Chord::parse("Cmd+F11"),
+            Ok(Chord::new(VirtualKeyCode::F11, ModifiersState::LOGO)));
+        assert!(Chord::parse("Hyper+Z").is_err());
+        assert!(Chord::parse("Ctrl+").is_err());
+        assert!(Chord::parse("Ctrl+Banana").is_err());
+    }
+}
@@ -0,0 +1,286 @@
+// Painting cells with the mouse.
+//
+// A Brush collects the path that the mouse moves along, and a BrushPass
+// paints that path into the Life grid with a small compute kernel that only
+// covers the cells the path touches.
+
+use std::borrow::Cow;
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    bindable::{Bindable, BindAccess, Binder, Buffer, Buffer2D, BufferType},
+    dimensions::{Dimensions, Rect},
+    directions::{RenderDir, RenderMotion, RenderSources},
+};
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the compute pipeline in WGSL.
+
+// This must match the value of the workgroup_size() annotation in brush.wgsl
+const WORKGROUP_SIZE: (u32, u32) = (8, 8);
+
+// How many line segments can be painted in one frame.
+const MAX_SEGMENTS: usize = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct BrushParams {
+    width: u32,
+    height: u32,
+    ox: i32,
+    oy: i32,
+    rw: u32,
+    rh: u32,
+    nsegs: u32,
+    shape: u32,
+    radius: f32,
+    value: f32,
+    _pad: [u32; 2],
+}
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BrushShape {
+    Round,
+    Square,
+    Diamond,
+}
+
+impl BrushShape {
+    pub fn next(self) -> Self {
+        match self {
+            BrushShape::Round => BrushShape::Square,
+            BrushShape::Square => BrushShape::Diamond,
+            BrushShape::Diamond => BrushShape::Round,
+        }
+    }
+}
+
+pub struct Brush {
+    size: u32,              // in cells
+    shape: BrushShape,
+    painting: bool,
+    lifted: bool,           // off the grid, for now
+    value: f32,             // what's being painted
+    last: (f32, f32),       // where the path got to
+    segments: Vec<[f32; 4]>,
+}
+
+impl Brush {
+    pub const MAX_SIZE: u32 = 256;
+
+    pub fn new() -> Self {
+        Brush {
+            size: 1,
+            shape: BrushShape::Round,
+            painting: false,
+            lifted: false,
+            value: 0.0,
+            last: (0.0, 0.0),
+            segments: Vec::new(),
+        }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    pub fn set_size(&mut self, size: u32) {
+        self.size = size.max(1).min(Brush::MAX_SIZE);
+    }
+
+    pub fn shape(&self) -> BrushShape {
+        self.shape
+    }
+
+    pub fn set_shape(&mut self, shape: BrushShape) {
+        self.shape = shape;
+    }
+
+    // Start painting the given value at a position in the grid.
+    pub fn start(
+        &mut self,
+        value: f32,
+        pos: (f32, f32),
+    ) {
+        self.painting = true;
+        self.lifted = false;
+        self.value = value;
+        self.last = pos;
+        self.segments.push([pos.0, pos.1, pos.0, pos.1]);
+    }
+
+    // Continue painting along a line to a new position.
+    pub fn move_to(
+        &mut self,
+        pos: (f32, f32),
+    ) {
+        if !self.painting {
+            return;
+        }
+        let from = if self.lifted { pos } else { self.last };
+        self.lifted = false;
+        let seg = [from.0, from.1, pos.0, pos.1];
+        if self.segments.len() < MAX_SEGMENTS {
+            self.segments.push(seg);
+        } else {
+            // Out of room; stretch the last segment to get here instead.
+            let last = self.segments.last_mut().unwrap();
+            last[2] = pos.0;
+            last[3] = pos.1;
+        }
+        self.last = pos;
+    }
+
+    // The mouse has gone off the grid.  Painting carries on where it
+    // comes back, without a line from where it left.
+    pub fn lift(&mut self) {
+        self.lifted = true;
+    }
+
+    // Stop painting.  Segments that haven't been applied yet still will be.
+    pub fn stop(&mut self) {
+        self.painting = false;
+    }
+
+    pub fn is_painting(&self) -> bool {
+        self.painting
+    }
+
+    // The region of a grid this big that the next apply() will paint, if any.
+    pub fn pending(
+        &self,
+        dim: Dimensions,
+    ) -> Option<Rect> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let region = self.bounds();
+        Some(Rect::new(region.x, region.y,
+            region.width.min(dim.width()), region.height.min(dim.height())))
+    }
+
+    fn radius(&self) -> f32 {
+        self.size as f32 / 2.0
+    }
+
+    // The cells that the pending segments could touch.
+    fn bounds(&self) -> Rect {
+        let r = self.radius();
+        let (mut x0, mut y0) = (f32::MAX, f32::MAX);
+        let (mut x1, mut y1) = (f32::MIN, f32::MIN);
+        for s in &self.segments {
+            x0 = x0.min(s[0].min(s[2]) - r);
+            y0 = y0.min(s[1].min(s[3]) - r);
+            x1 = x1.max(s[0].max(s[2]) + r);
+            y1 = y1.max(s[1].max(s[3]) + r);
+        }
+        Rect::covering((x0, y0), (x1, y1))
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct BrushPass {
+    shader: wgpu::ShaderModule,
+    params: Buffer,
+    segments: Buffer,
+    pipeline: Option<wgpu::ComputePipeline>,
+    bind_groups: Option<RenderMotion<wgpu::BindGroup>>,
+}
+
+impl BrushPass {
+    pub fn new(
+        device: &wgpu::Device,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("brush"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    include_str!("brush.wgsl"))),
+            });
+
+        let params = Buffer::new(device, "brush parameters",
+            BufferType::Uniform, std::mem::size_of::<BrushParams>());
+        let segments = Buffer::new(device, "brush segments",
+            BufferType::Storage, MAX_SEGMENTS * std::mem::size_of::<[f32; 4]>());
+
+        BrushPass {
+            shader,
+            params,
+            segments,
+            pipeline: None,
+            bind_groups: None,
+        }
+    }
+
+    // Bind to the Life grid's cell buffers.
+    // This has to be done again whenever those buffers are replaced.
+    pub fn bind(
+        &mut self,
+        device: &wgpu::Device,
+        cell_buffers: &RenderSources<Buffer2D<f32>>,
+    ) {
+        let params = &self.params;
+        let segments = &self.segments;
+        let (pipeline, bind_groups) = Binder::bind_up_dir(
+            device, &self.shader, "brush",
+            &|dir| {
+                let v: Vec<(_, &dyn Bindable)> = vec![
+                    (BindAccess::ReadOnly,  params),
+                    (BindAccess::ReadOnly,  segments),
+                    (BindAccess::WriteOnly, cell_buffers.src(dir)),
+                ];
+                v
+            }
+        );
+        self.pipeline = Some(pipeline);
+        self.bind_groups = Some(bind_groups);
+    }
+
+    // Paint the brush's pending segments into the source cells for the
+    // given direction.  Returns the region that was painted, if any.
+    pub fn apply(
+        &self,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        dir: RenderDir,
+        dim: Dimensions,
+        brush: &mut Brush,
+    ) -> Option<Rect> {
+        let region = brush.pending(dim)?;
+        let params = BrushParams {
+            width: dim.width(),
+            height: dim.height(),
+            ox: region.x,
+            oy: region.y,
+            rw: region.width,
+            rh: region.height,
+            nsegs: brush.segments.len() as u32,
+            shape: brush.shape as u32,
+            radius: brush.radius(),
+            value: brush.value,
+            _pad: [0; 2],
+        };
+        queue.write_buffer(self.params.buf(), 0, bytemuck::bytes_of(&params));
+        queue.write_buffer(self.segments.buf(), 0,
+            bytemuck::cast_slice(&brush.segments));
+        brush.segments.clear();
+
+        let mut cpass = command_encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("brush")
+            });
+        cpass.set_pipeline(self.pipeline.as_ref().expect("brush isn't bound"));
+        cpass.set_bind_group(0,
+            self.bind_groups.as_ref().expect("brush isn't bound").get(dir), &[]);
+        cpass.dispatch(
+            (params.rw + WORKGROUP_SIZE.0 - 1) / WORKGROUP_SIZE.0,
+            (params.rh + WORKGROUP_SIZE.1 - 1) / WORKGROUP_SIZE.1,
+            1
+        );
+
+        Some(region)
+    }
+}
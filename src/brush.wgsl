@@ -0,0 +1,81 @@
+[[block]]
+struct BrushParams {
+    width: u32;
+    height: u32;
+    ox: i32;            // origin of the region being painted
+    oy: i32;
+    rw: u32;            // size of the region being painted
+    rh: u32;
+    nsegs: u32;
+    shape: u32;         // 0 = round, 1 = square, 2 = diamond
+    radius: f32;
+    value: f32;
+    pad0: u32;
+    pad1: u32;
+};
+
+[[block]]
+struct Segments {
+    segs: array<vec4<f32>>;
+};
+
+[[block]]
+struct Cells {
+    cells: array<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: BrushParams;
+[[group(0), binding(1)]] var<storage, read> segments: Segments;
+[[group(0), binding(2)]] var<storage, read_write> cells: Cells;
+
+// The distance from a point to a line segment from s.xy to s.zw,
+// measured in a way that gives the brush its shape.
+fn seg_dist(p: vec2<f32>, s: vec4<f32>) -> f32 {
+    let a: vec2<f32> = s.xy;
+    let ab: vec2<f32> = s.zw - s.xy;
+    let len2: f32 = dot(ab, ab);
+
+    var t: f32 = 0.0;
+    if (len2 > 0.0) {
+        t = clamp(dot(p - a, ab) / len2, 0.0, 1.0);
+    }
+    let d: vec2<f32> = abs(p - (a + t * ab));
+
+    if (params.shape == 1u32) {
+        return max(d.x, d.y);
+    }
+    if (params.shape == 2u32) {
+        return d.x + d.y;
+    }
+    return length(d);
+}
+
+// ----------------------------------------------------------------------
+// Kernel for painting a value into every cell under the brush's path.
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn brush([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    if (global_id.x >= params.rw || global_id.y >= params.rh) {
+        return;
+    }
+
+    let X: i32 = params.ox + i32(global_id.x);
+    let Y: i32 = params.oy + i32(global_id.y);
+    let p: vec2<f32> = vec2<f32>(f32(X) + 0.5, f32(Y) + 0.5);
+
+    var hit: bool = false;
+    for (var i: u32 = 0u32; i < params.nsegs; i = i + 1u32) {
+        if (seg_dist(p, segments.segs[i]) <= params.radius) {
+            hit = true;
+        }
+    }
+
+    if (hit) {
+        // The grid wraps around at the edges.
+        let W: i32 = i32(params.width);
+        let H: i32 = i32(params.height);
+        let xw: u32 = u32(((X % W) + W) % W);
+        let yw: u32 = u32(((Y % H) + H) % H);
+        cells.cells[yw * params.width + xw] = params.value;
+    }
+}
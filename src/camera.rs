@@ -0,0 +1,148 @@
+// Which part of the Life grid is shown in the window.
+//
+// Positions in the grid are given as texture coordinates, which run from
+// (0, 0) at the bottom left of the grid to (1, 1) at the top right.  The grid
+// wraps around, so coordinates outside that range are still meaningful.
+//
+// The grid and the window can be any sizes.  Cells are always square, and
+// fully zoomed out, the whole grid fits in the window with black bars
+// along the sides that it doesn't fill.
+
+use bytemuck::{Pod, Zeroable};
+
+use crate::dimensions::Dimensions;
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the vertex shader in WGSL.
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+pub struct CameraUniform {
+    center: [f32; 2],
+    extent: [f32; 2],       // how much of the grid the window spans
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct Camera {
+    center: (f32, f32),     // the grid position in the middle of the window
+    zoom: f32,              // 1.0 shows the whole grid
+    window: Dimensions,
+    grid: Dimensions,
+}
+
+impl Camera {
+    pub const MIN_ZOOM: f32 = 1.0;
+    pub const MAX_ZOOM: f32 = 256.0;
+
+    pub fn new(
+        window: Dimensions,
+        grid: Dimensions,
+    ) -> Self {
+        Camera {
+            center: (0.5, 0.5),
+            zoom: 1.0,
+            window,
+            grid,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Camera::new(self.window, self.grid);
+    }
+
+    // The window or the grid has changed size.
+    pub fn resize(
+        &mut self,
+        window: Dimensions,
+        grid: Dimensions,
+    ) {
+        self.window = window;
+        self.grid = grid;
+    }
+
+    // How many pixels wide a cell is.  Fully zoomed out, this is a whole
+    // number if it can be, so that all of the cells are the same size.
+    pub fn cell_size(&self) -> f32 {
+        let fit = (self.window.width() as f32 / self.grid.width() as f32)
+            .min(self.window.height() as f32 / self.grid.height() as f32);
+        let fit = if fit >= 1.0 { fit.floor() } else { fit };
+        fit * self.zoom
+    }
+
+    // How much of the grid the window spans, in each direction.
+    fn extent(&self) -> (f32, f32) {
+        let cell = self.cell_size();
+        (
+            self.window.width() as f32 / (cell * self.grid.width() as f32),
+            self.window.height() as f32 / (cell * self.grid.height() as f32),
+        )
+    }
+
+    // Convert a position in the window, in physical pixels from the top
+    // left corner, to a position in the grid.
+    pub fn screen_to_grid(
+        &self,
+        (x, y): (f64, f64),
+    ) -> (f32, f32) {
+        let sx = x as f32 / self.window.width() as f32;
+        let sy = 1.0 - y as f32 / self.window.height() as f32;
+        let (ex, ey) = self.extent();
+        (
+            self.center.0 + (sx - 0.5) * ex,
+            self.center.1 + (sy - 0.5) * ey,
+        )
+    }
+
+    // Whether a position in the window is over the grid, and not the
+    // bars beside it.  The renderer draws no more than half of the grid
+    // either side of the center.
+    pub fn on_grid(
+        &self,
+        pos: (f64, f64),
+    ) -> bool {
+        let (u, v) = self.screen_to_grid(pos);
+        (u - self.center.0).abs() <= 0.5 && (v - self.center.1).abs() <= 0.5
+    }
+
+    // Move the view by a distance in physical pixels, so that whatever was
+    // under the mouse stays under the mouse.
+    pub fn pan(
+        &mut self,
+        (dx, dy): (f64, f64),
+    ) {
+        let (ex, ey) = self.extent();
+        self.center.0 -= dx as f32 / self.window.width() as f32 * ex;
+        self.center.1 += dy as f32 / self.window.height() as f32 * ey;
+        self.wrap();
+    }
+
+    // Zoom in (factor > 1) or out (factor < 1), keeping the grid position
+    // at the given window position fixed.
+    pub fn zoom_at(
+        &mut self,
+        factor: f32,
+        pos: (f64, f64),
+    ) {
+        let before = self.screen_to_grid(pos);
+        self.zoom = (self.zoom * factor).max(Camera::MIN_ZOOM).min(Camera::MAX_ZOOM);
+        let after = self.screen_to_grid(pos);
+        self.center.0 += before.0 - after.0;
+        self.center.1 += before.1 - after.1;
+        self.wrap();
+    }
+
+    // Keep the center inside the grid, so precision doesn't drift away.
+    fn wrap(&mut self) {
+        self.center.0 = self.center.0.rem_euclid(1.0);
+        self.center.1 = self.center.1.rem_euclid(1.0);
+    }
+
+    pub fn uniform(&self) -> CameraUniform {
+        let (ex, ey) = self.extent();
+        CameraUniform {
+            center: [self.center.0, self.center.1],
+            extent: [ex, ey],
+        }
+    }
+}
@@ -0,0 +1,137 @@
+// An offscreen render target that can be copied back to the CPU.
+//
+// The surface texture can only be used as a render attachment, so anything
+// that wants to look at the rendered pixels (screenshots, recordings) has
+// the Renderer draw into one of these instead.
+
+use std::num::NonZeroU32;
+
+use crate::dimensions::Dimensions;
+
+pub struct CaptureTarget {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    dim: Dimensions,
+    format: wgpu::TextureFormat,
+}
+
+impl CaptureTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        dim: Dimensions,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("capture target"),
+            size: wgpu::Extent3d {
+                width: dim.width(),
+                height: dim.height(),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                 | wgpu::TextureUsages::COPY_SRC,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        CaptureTarget {
+            texture,
+            view,
+            dim,
+            format,
+        }
+    }
+
+    pub fn view(&self) -> &wgpu::TextureView {
+        &self.view
+    }
+
+    pub fn dim(&self) -> Dimensions {
+        self.dim
+    }
+
+    // Rows copied out of a texture have to be padded out to a multiple
+    // of COPY_BYTES_PER_ROW_ALIGNMENT.
+    fn padded_bytes_per_row(&self) -> u32 {
+        let unpadded = self.dim.width() * 4;
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        (unpadded + align - 1) / align * align
+    }
+
+    // The size of a buffer that can hold the output of enqueue_copyout().
+    pub fn buffer_size(&self) -> u64 {
+        self.padded_bytes_per_row() as u64 * self.dim.height() as u64
+    }
+
+    // Create a buffer suitable for passing to enqueue_copyout().
+    pub fn create_buffer(
+        &self,
+        device: &wgpu::Device,
+        label: &str,
+    ) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: self.buffer_size(),
+            usage: wgpu::BufferUsages::COPY_DST
+                 | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        })
+    }
+
+    // Enqueue a copy of the rendered image into the given buffer.
+    pub fn enqueue_copyout(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        buffer: &wgpu::Buffer,
+    ) {
+        command_encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::ImageCopyBuffer {
+                buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(self.padded_bytes_per_row()),
+                    rows_per_image: None,
+                },
+            },
+            wgpu::Extent3d {
+                width: self.dim.width(),
+                height: self.dim.height(),
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    // Strip the row padding from the mapped contents of a buffer that was
+    // filled by enqueue_copyout(), and convert the pixels to RGBA order.
+    pub fn to_rgba(
+        &self,
+        padded: &[u8],
+    ) -> Vec<u8> {
+        let unpadded = (self.dim.width() * 4) as usize;
+        let padded_row = self.padded_bytes_per_row() as usize;
+        let swap = matches!(self.format,
+            wgpu::TextureFormat::Bgra8Unorm |
+            wgpu::TextureFormat::Bgra8UnormSrgb);
+
+        let mut rgba = Vec::with_capacity(unpadded * self.dim.height() as usize);
+        for row in padded.chunks(padded_row).take(self.dim.height() as usize) {
+            for px in row[..unpadded].chunks_exact(4) {
+                if swap {
+                    rgba.extend_from_slice(&[px[2], px[1], px[0], px[3]]);
+                } else {
+                    rgba.extend_from_slice(px);
+                }
+            }
+        }
+        rgba
+    }
+}
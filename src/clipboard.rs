@@ -0,0 +1,289 @@
+// Copying, cutting and pasting rectangles of cells.
+//
+// The clipboard lives on the GPU, in a Buffer2D that's the size of whatever
+// was last copied into it.  Rotating and flipping it doesn't touch the
+// contents; the orientation is applied when it's pasted (and when the
+// renderer draws a preview of where it would go).
+
+use std::borrow::Cow;
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    bindable::{Bindable, BindAccess, Binder, Buffer, Buffer2D, BufferType},
+    dimensions::{Dimensions, Rect},
+};
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the compute pipeline in WGSL.
+
+// This must match the value of the workgroup_size() annotation in clipboard.wgsl
+const WORKGROUP_SIZE: (u32, u32) = (8, 8);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct ClipParams {
+    width: u32,
+    height: u32,
+    cw: u32,
+    ch: u32,
+    ox: i32,
+    oy: i32,
+    orient: u32,
+    mode: u32,
+    threshold: f32,
+    cut: u32,
+    _pad: [u32; 2],
+}
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PasteMode {
+    Or,
+    Xor,
+    And,
+    Overwrite,
+}
+
+impl PasteMode {
+    pub fn next(self) -> Self {
+        match self {
+            PasteMode::Or => PasteMode::Xor,
+            PasteMode::Xor => PasteMode::And,
+            PasteMode::And => PasteMode::Overwrite,
+            PasteMode::Overwrite => PasteMode::Or,
+        }
+    }
+}
+
+// How the clipboard is turned around before it's pasted.
+// Rotation happens first, then flipping.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Orientation {
+    turns: u32,         // quarter turns counterclockwise
+    flip_x: bool,
+    flip_y: bool,
+}
+
+impl Orientation {
+    pub fn rotate(&mut self) {
+        // Turning a flipped pattern one way is the same as turning
+        // the unflipped pattern the other way and then flipping it.
+        let turn = if self.flip_x != self.flip_y { 3 } else { 1 };
+        self.turns = (self.turns + turn) % 4;
+    }
+
+    pub fn flip_x(&mut self) {
+        self.flip_x = !self.flip_x;
+    }
+
+    pub fn flip_y(&mut self) {
+        self.flip_y = !self.flip_y;
+    }
+
+    // The size of something this big, after it has been turned.
+    pub fn apply(
+        &self,
+        dim: Dimensions,
+    ) -> Dimensions {
+        if self.turns % 2 == 1 {
+            Dimensions::new(dim.height(), dim.width())
+        } else {
+            dim
+        }
+    }
+
+    // The form the shaders use.
+    pub fn bits(&self) -> u32 {
+        self.turns
+            | if self.flip_x { 4 } else { 0 }
+            | if self.flip_y { 8 } else { 0 }
+    }
+}
+
+pub struct Clipboard {
+    buf: Buffer2D<f32>,
+    empty: bool,
+    pub orientation: Orientation,
+    pub mode: PasteMode,
+}
+
+impl Clipboard {
+    pub fn new(
+        device: &wgpu::Device,
+    ) -> Self {
+        Clipboard {
+            buf: Buffer2D::new(device, "clipboard", Dimensions::new(1, 1)),
+            empty: true,
+            orientation: Orientation::default(),
+            mode: PasteMode::Or,
+        }
+    }
+
+    pub fn buf(&self) -> &Buffer2D<f32> {
+        &self.buf
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.empty
+    }
+
+    // Replace the contents of the clipboard.
+    pub fn set(
+        &mut self,
+        buf: Buffer2D<f32>,
+    ) {
+        self.buf = buf;
+        self.empty = false;
+        self.orientation = Orientation::default();
+    }
+
+    // Replace the contents of the clipboard with cells from the CPU.
+    pub fn load(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        dim: Dimensions,
+        cells: &Vec<f32>,
+    ) {
+        let buf = Buffer2D::new(device, "clipboard", dim);
+        buf.copyin_vec(device, queue, cells);
+        self.set(buf);
+    }
+
+    // The size of the clipboard, as it would be pasted.
+    pub fn pasted_dim(&self) -> Dimensions {
+        self.orientation.apply(self.buf.dim())
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct ClipboardPass {
+    shader: wgpu::ShaderModule,
+}
+
+impl ClipboardPass {
+    pub fn new(
+        device: &wgpu::Device,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("clipboard"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    include_str!("clipboard.wgsl"))),
+            });
+
+        ClipboardPass {
+            shader,
+        }
+    }
+
+    fn run(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entry_point: &str,
+        params: &ClipParams,
+        grid: &Buffer2D<f32>,
+        clip: &Buffer2D<f32>,
+    ) {
+        // The kernels cover the clipboard, once it's been turned around.
+        let size = if params.orient & 1 != 0 {
+            Dimensions::new(params.ch, params.cw)
+        } else {
+            Dimensions::new(params.cw, params.ch)
+        };
+
+        let param_buf = Buffer::new_init(
+            device,
+            "clipboard parameters",
+            BufferType::Uniform,
+            bytemuck::bytes_of(params)
+        );
+
+        let args: Vec<(_, &dyn Bindable)> = vec![
+            (BindAccess::ReadOnly,  &param_buf),
+            (BindAccess::WriteOnly, grid),
+            (BindAccess::WriteOnly, clip),
+        ];
+        let (pipeline, bind_group) =
+            Binder::bind_up(device, &self.shader, entry_point, &args);
+
+        let mut command_encoder = device.create_command_encoder(
+            &wgpu::CommandEncoderDescriptor {
+                label: Some(entry_point)
+            });
+        {
+            let mut cpass = command_encoder.begin_compute_pass(
+                &wgpu::ComputePassDescriptor {
+                    label: Some(entry_point)
+                });
+            cpass.set_pipeline(&pipeline);
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.dispatch(
+                (size.width() + WORKGROUP_SIZE.0 - 1) / WORKGROUP_SIZE.0,
+                (size.height() + WORKGROUP_SIZE.1 - 1) / WORKGROUP_SIZE.1,
+                1
+            );
+        }
+        queue.submit(Some(command_encoder.finish()));
+    }
+
+    // Copy a rectangle of the grid into a new buffer, and clear it from
+    // the grid if this is a cut.
+    pub fn copy(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        grid: &Buffer2D<f32>,
+        rect: Rect,
+        cut: bool,
+    ) -> Buffer2D<f32> {
+        let dim = Dimensions::new(rect.width.max(1), rect.height.max(1));
+        let clip = Buffer2D::new(device, "clipboard", dim);
+        let params = ClipParams {
+            width: grid.dim().width(),
+            height: grid.dim().height(),
+            cw: dim.width(),
+            ch: dim.height(),
+            ox: rect.x,
+            oy: rect.y,
+            orient: 0,
+            mode: 0,
+            threshold: 0.0,
+            cut: cut as u32,
+            _pad: [0; 2],
+        };
+        self.run(device, queue, "copy", &params, grid, &clip);
+        clip
+    }
+
+    // Combine the clipboard with the grid, with its bottom left corner at
+    // the given cell.
+    pub fn paste(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        grid: &Buffer2D<f32>,
+        clipboard: &Clipboard,
+        origin: (i32, i32),
+        threshold: f32,
+    ) {
+        let dim = clipboard.buf.dim();
+        let params = ClipParams {
+            width: grid.dim().width(),
+            height: grid.dim().height(),
+            cw: dim.width(),
+            ch: dim.height(),
+            ox: origin.0,
+            oy: origin.1,
+            orient: clipboard.orientation.bits(),
+            mode: clipboard.mode as u32,
+            threshold,
+            cut: 0,
+            _pad: [0; 2],
+        };
+        self.run(device, queue, "paste", &params, grid, &clipboard.buf);
+    }
+}
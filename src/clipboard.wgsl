@@ -0,0 +1,131 @@
+[[block]]
+struct ClipParams {
+    width: u32;         // of the Life grid
+    height: u32;
+    cw: u32;            // of the clipboard, before it's transformed
+    ch: u32;
+    ox: i32;            // where the clipboard goes in the grid
+    oy: i32;
+    orient: u32;        // bits 0-1: quarter turns; bit 2: flip x; bit 3: flip y
+    mode: u32;          // 0 = or, 1 = xor, 2 = and, 3 = overwrite
+    threshold: f32;
+    cut: u32;
+    pad0: u32;
+    pad1: u32;
+};
+
+[[block]]
+struct Cells {
+    cells: array<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: ClipParams;
+[[group(0), binding(1)]] var<storage, read_write> grid: Cells;
+[[group(0), binding(2)]] var<storage, read_write> clip: Cells;
+
+// The index in the grid of a position relative to the clipboard's origin.
+// The grid wraps around at the edges.
+fn grid_index(x: u32, y: u32) -> u32 {
+    let W: i32 = i32(params.width);
+    let H: i32 = i32(params.height);
+    let gx: u32 = u32(((params.ox + i32(x)) % W + W) % W);
+    let gy: u32 = u32(((params.oy + i32(y)) % H + H) % H);
+    return gy * params.width + gx;
+}
+
+// The size of the clipboard once it has been rotated.
+fn turned_size() -> vec2<u32> {
+    if ((params.orient & 1u32) != 0u32) {
+        return vec2<u32>(params.ch, params.cw);
+    }
+    return vec2<u32>(params.cw, params.ch);
+}
+
+// Map a position in the rotated and flipped clipboard back to
+// the index of the clipboard cell that ends up there.
+fn clip_index(x: u32, y: u32) -> u32 {
+    let size: vec2<u32> = turned_size();
+    var p: vec2<u32> = vec2<u32>(x, y);
+    if ((params.orient & 4u32) != 0u32) {
+        p.x = size.x - 1u32 - p.x;
+    }
+    if ((params.orient & 8u32) != 0u32) {
+        p.y = size.y - 1u32 - p.y;
+    }
+
+    // undo the quarter turns (counterclockwise)
+    var c: vec2<u32> = p;
+    let turns: u32 = params.orient & 3u32;
+    if (turns == 1u32) {
+        c = vec2<u32>(p.y, params.ch - 1u32 - p.x);
+    }
+    if (turns == 2u32) {
+        c = vec2<u32>(params.cw - 1u32 - p.x, params.ch - 1u32 - p.y);
+    }
+    if (turns == 3u32) {
+        c = vec2<u32>(params.cw - 1u32 - p.y, p.x);
+    }
+    return c.y * params.cw + c.x;
+}
+
+// ----------------------------------------------------------------------
+// Kernel for copying a rectangle of the grid into the clipboard,
+// and clearing it if this is a cut.
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn copy([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    let X: u32 = global_id.x;
+    let Y: u32 = global_id.y;
+    if (X >= params.cw || Y >= params.ch) {
+        return;
+    }
+
+    let g: u32 = grid_index(X, Y);
+    clip.cells[Y * params.cw + X] = grid.cells[g];
+    if (params.cut != 0u32) {
+        grid.cells[g] = 0.0;
+    }
+}
+
+// ----------------------------------------------------------------------
+// Kernel for combining the (transformed) clipboard with the grid.
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn paste([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    let X: u32 = global_id.x;
+    let Y: u32 = global_id.y;
+    let size: vec2<u32> = turned_size();
+    if (X >= size.x || Y >= size.y) {
+        return;
+    }
+
+    let g: u32 = grid_index(X, Y);
+    let old: f32 = grid.cells[g];
+    let new: f32 = clip.cells[clip_index(X, Y)];
+    let old_alive: bool = old > params.threshold;
+    let new_alive: bool = new > params.threshold;
+
+    var result: f32 = old;
+    if (params.mode == 0u32) {
+        if (new_alive) {
+            result = new;
+        }
+    }
+    if (params.mode == 1u32) {
+        if (new_alive && old_alive) {
+            result = 0.0;
+        }
+        if (new_alive && !old_alive) {
+            result = new;
+        }
+    }
+    if (params.mode == 2u32) {
+        if (!(new_alive && old_alive)) {
+            result = 0.0;
+        }
+    }
+    if (params.mode == 3u32) {
+        result = new;
+    }
+    grid.cells[g] = result;
+}
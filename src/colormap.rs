@@ -0,0 +1,403 @@
+// Colormaps: how the values of live cells are turned into colors.
+//
+// A colormap is a ramp of PALETTE_SIZE colors, going from cells that are
+// only just alive up to cells that are as alive as can be.  The renderer
+// looks colors up in a texture made from the ramp, and the GIF exporter
+// uses it as the GIF's palette.  Dead cells are always black.
+//
+// Colormaps can also be read from files: either a PNG, whose top row of
+// pixels is used, or text listing hex color stops, like
+//
+//     // deep sea
+//     #000033 #0066cc #33ccff
+//     #ffffff
+
+use std::path::Path;
+
+use crate::bindable::{Bindable, BindAccess};
+
+// Convert a hue/saturation/value triple into RGB.
+fn hsv_to_rgb(
+    h: f32,
+    s: f32,
+    v: f32,
+) -> [f32; 3] {
+    let h = h * 6.0;
+    let w = h as i32;
+    let f = h - w as f32;
+    let p = v * (1.0 - s);
+    let q = v * (1.0 - (s * f));
+    let t = v * (1.0 - (s * (1.0 - f)));
+
+    match w {
+        1 => [q, v, p],
+        2 => [p, v, t],
+        3 => [p, q, v],
+        4 => [t, p, v],
+        5 => [v, p, q],
+        _ => [v, t, p],
+    }
+}
+
+fn to_u8(
+    rgb: [f32; 3],
+) -> [u8; 3] {
+    [
+        (rgb[0] * 255.0).round() as u8,
+        (rgb[1] * 255.0).round() as u8,
+        (rgb[2] * 255.0).round() as u8,
+    ]
+}
+
+// Number of entries in a palette.  Entry 0 is used for dead cells.
+pub const PALETTE_SIZE: usize = 256;
+
+// Map a cell value onto an index into Colormap::palette().
+pub fn index(
+    val: f32,
+    thresh: f32,
+) -> u8 {
+    if val <= thresh {
+        0
+    } else {
+        let a = ((val - thresh) / (1.0 - thresh)).min(1.0);
+        1 + (a * (PALETTE_SIZE - 2) as f32).round() as u8
+    }
+}
+
+// The stops of the matplotlib colormaps, evenly spaced.
+const VIRIDIS: &[u32] = &[
+    0x440154, 0x482878, 0x3e4989, 0x31688e, 0x26828e,
+    0x1f9e89, 0x35b779, 0x6ece58, 0xb5de2b, 0xfde725,
+];
+const MAGMA: &[u32] = &[
+    0x000004, 0x180f3d, 0x440f76, 0x721f81, 0x9e2f7f,
+    0xcd4071, 0xf1605d, 0xfd9668, 0xfeca8d, 0xfcfdbf,
+];
+// This one was designed to look the same with most kinds of color blindness.
+const CIVIDIS: &[u32] = &[
+    0x00224e, 0x123570, 0x3b496c, 0x575d6d, 0x707173,
+    0x8a8779, 0xa69d75, 0xc4b56c, 0xe4cf5b, 0xfee838,
+];
+const GREYSCALE: &[u32] = &[
+    0x333333, 0xffffff,
+];
+
+fn rgb(
+    hex: u32,
+) -> [u8; 3] {
+    [(hex >> 16) as u8, (hex >> 8) as u8, hex as u8]
+}
+
+pub struct Colormap {
+    pub name: String,
+    colors: Vec<[u8; 3]>,   // PALETTE_SIZE of them
+}
+
+impl Colormap {
+    // Spread colors evenly over the ramp, blending between them.
+    pub fn from_stops(
+        name: &str,
+        stops: &[[u8; 3]],
+    ) -> Colormap {
+        let colors = (0..PALETTE_SIZE).map(|i| {
+            if stops.len() == 1 {
+                return stops[0];
+            }
+            let at = i as f32 / (PALETTE_SIZE - 1) as f32 * (stops.len() - 1) as f32;
+            let n = (at as usize).min(stops.len() - 2);
+            let f = at - n as f32;
+            let (a, b) = (stops[n], stops[n + 1]);
+            let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * f).round() as u8;
+            [mix(a[0], b[0]), mix(a[1], b[1]), mix(a[2], b[2])]
+        }).collect();
+
+        Colormap {
+            name: name.to_string(),
+            colors,
+        }
+    }
+
+    fn from_fn(
+        name: &str,
+        f: impl Fn(f32) -> [f32; 3],
+    ) -> Colormap {
+        Colormap {
+            name: name.to_string(),
+            colors: (0..PALETTE_SIZE)
+                .map(|i| to_u8(f(i as f32 / (PALETTE_SIZE - 1) as f32)))
+                .collect(),
+        }
+    }
+
+    fn from_hex(
+        name: &str,
+        stops: &[u32],
+    ) -> Colormap {
+        Colormap::from_stops(name, &stops.iter().map(|&hex| rgb(hex)).collect::<Vec<_>>())
+    }
+
+    // The colormaps that are always there.  The first is the default.
+    pub fn built_in() -> Vec<Colormap> {
+        vec![
+            // The original coloring: blue for barely alive, through to red.
+            Colormap::from_fn("classic", |a| hsv_to_rgb((1.0 - a) * 0.7, 1.0, 1.0)),
+            Colormap::from_hex("viridis", VIRIDIS),
+            Colormap::from_hex("magma", MAGMA),
+            Colormap::from_hex("cividis", CIVIDIS),
+            Colormap::from_hex("greyscale", GREYSCALE),
+            // All the way around the color wheel, so the ends meet.
+            Colormap::from_fn("cyclic", |a| hsv_to_rgb(a, 0.8, 1.0)),
+        ]
+    }
+
+    // A built-in colormap, or failing that, one from a file.
+    pub fn named(
+        name: &str,
+    ) -> Result<Colormap, String> {
+        if let Some(map) = Colormap::built_in().into_iter().find(|map| map.name == name) {
+            return Ok(map);
+        }
+        if cfg!(target_arch = "wasm32") {
+            return Err(format!("no colormap named \"{}\"", name));
+        }
+        Colormap::load(Path::new(name)).map_err(|e| format!("{}: {}", name, e))
+    }
+
+    // Read a colormap from a PNG or a list of hex colors.
+    pub fn load(
+        path: &Path,
+    ) -> Result<Colormap, String> {
+        let name = path.file_stem().map_or_else(String::new,
+            |s| s.to_string_lossy().into_owned());
+        let stops = if path.extension().map_or(false, |ext| ext == "png") {
+            Colormap::read_png(path)?
+        } else {
+            let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+            Colormap::parse_stops(&text)?
+        };
+        if stops.is_empty() {
+            return Err("no colors in it".to_string());
+        }
+        Ok(Colormap::from_stops(&name, &stops))
+    }
+
+    // Hex colors like "#40a0ff" or "40a0ff", separated by white space or
+    // commas.  Anything after "//" on a line is ignored.
+    fn parse_stops(
+        text: &str,
+    ) -> Result<Vec<[u8; 3]>, String> {
+        text.lines()
+            .map(|line| line.split("//").next().unwrap_or(""))
+            .flat_map(|line| line.split(|c: char| c.is_whitespace() || c == ','))
+            .filter(|word| !word.is_empty())
+            .map(|word| {
+                let hex = word.strip_prefix('#').unwrap_or(word);
+                match u32::from_str_radix(hex, 16) {
+                    Ok(value) if hex.len() == 6 => Ok(rgb(value)),
+                    _ => Err(format!("bad color \"{}\"", word)),
+                }
+            })
+            .collect()
+    }
+
+    // The top row of pixels in a PNG.
+    fn read_png(
+        path: &Path,
+    ) -> Result<Vec<[u8; 3]>, String> {
+        let file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+        let mut decoder = png::Decoder::new(file);
+        decoder.set_transformations(png::Transformations::EXPAND
+            | png::Transformations::STRIP_16);
+        let (info, mut reader) = decoder.read_info().map_err(|e| e.to_string())?;
+        let mut pixels = vec![0; info.buffer_size()];
+        reader.next_frame(&mut pixels).map_err(|e| e.to_string())?;
+
+        let (color_type, _) = reader.output_color_type();
+        let samples = color_type.samples();
+        let row = &pixels[..(info.width as usize * samples)];
+        Ok(row.chunks(samples).map(|p| match color_type {
+            png::ColorType::Grayscale | png::ColorType::GrayscaleAlpha =>
+                [p[0], p[0], p[0]],
+            _ => [p[0], p[1], p[2]],
+        }).collect())
+    }
+
+    // The GIF palette: black for dead cells, then the ramp.
+    pub fn palette(&self) -> Vec<[u8; 3]> {
+        std::iter::once([0, 0, 0])
+            .chain((1..PALETTE_SIZE).map(|i| {
+                self.colors[(i - 1) * (PALETTE_SIZE - 1) / (PALETTE_SIZE - 2)]
+            }))
+            .collect()
+    }
+
+    // The color a fraction a of the way along the ramp, picked the same
+    // way as palette_color() in renderer.wgsl.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn color(
+        &self,
+        a: f32,
+    ) -> [u8; 3] {
+        let i = (a.clamp(0.0, 1.0) * (self.colors.len() - 1) as f32 + 0.5) as usize;
+        self.colors[i.min(self.colors.len() - 1)]
+    }
+
+    // The ramp as RGBA, for the palette texture.
+    fn rgba(&self) -> Vec<u8> {
+        self.colors.iter().flat_map(|&[r, g, b]| [r, g, b, 255]).collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// The colormaps to choose from.
+pub struct Colormaps {
+    maps: Vec<Colormap>,
+    selected: usize,
+    changed: bool,          // since the palette was last uploaded
+}
+
+impl Colormaps {
+    pub fn new() -> Self {
+        Colormaps {
+            maps: Colormap::built_in(),
+            selected: 0,
+            changed: true,
+        }
+    }
+
+    // Whether another colormap has been chosen since the last time this
+    // was asked, so that the palette only gets uploaded when it changes.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::replace(&mut self.changed, false)
+    }
+
+    pub fn current(&self) -> &Colormap {
+        &self.maps[self.selected]
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.maps.len();
+        self.changed = true;
+    }
+
+    // Choose a colormap by name, or failing that, load it from a file.
+    pub fn choose(
+        &mut self,
+        name: &str,
+    ) -> Result<(), String> {
+        if let Some(i) = self.maps.iter().position(|map| map.name == name) {
+            self.selected = i;
+            self.changed = true;
+            return Ok(());
+        }
+        if cfg!(target_arch = "wasm32") {
+            return Err(format!("no colormap named \"{}\"", name));
+        }
+        let map = Colormap::load(Path::new(name))
+            .map_err(|e| format!("{}: {}", name, e))?;
+        self.maps.push(map);
+        self.selected = self.maps.len() - 1;
+        self.changed = true;
+        Ok(())
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.maps.iter().map(|map| map.name.as_str()).collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// The current colormap, as a texture that the renderer can look colors up in.
+pub struct PaletteTexture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+}
+
+impl PaletteTexture {
+    pub fn new(
+        device: &wgpu::Device,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("palette"),
+            size: wgpu::Extent3d {
+                width: PALETTE_SIZE as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        PaletteTexture {
+            texture,
+            view,
+        }
+    }
+
+    pub fn set(
+        &self,
+        queue: &wgpu::Queue,
+        colormap: &Colormap,
+    ) {
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &colormap.rgba(),
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: None,
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width: PALETTE_SIZE as u32,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
+
+impl Bindable for PaletteTexture {
+    fn binding_resource(
+        &self
+    ) -> wgpu::BindingResource {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+
+    fn binding_type(
+        &self,
+        _: BindAccess,
+    ) -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D1,
+            multisampled: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stops() {
+        assert_eq!(Colormap::parse_stops("#000000, #ff8000  // dark to orange\n40a0ff\n"),
+            Ok(vec![[0x00, 0x00, 0x00], [0xff, 0x80, 0x00], [0x40, 0xa0, 0xff]]));
+        assert_eq!(Colormap::parse_stops("// nothing\n"), Ok(Vec::new()));
+        assert!(Colormap::parse_stops("#fff").is_err());
+        assert!(Colormap::parse_stops("#gggggg").is_err());
+    }
+}
@@ -0,0 +1,436 @@
+// Comparing runs side by side.
+//
+// More Life grids are started from a copy of the main one, each with a
+// different rule or threshold, and they step in lockstep with it.  The
+// window is split into panes: the main run goes in the top left, then the
+// others across and down, and then, if it's showing, a difference view of
+// where the main run and the first of the others disagree.
+//
+// Only the main run can be edited; the others just follow along.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use bytemuck::{Pod, Zeroable};
+use rand::SeedableRng;
+
+use crate::{
+    bindable::{Bindable, BindAccess, Binder, Buffer, BufferType, Texture},
+    camera::Camera,
+    colormap::Colormap,
+    dimensions::{Dimensions, Rect},
+    life::Life,
+    renderer::{Overlay, Renderer},
+    rule::Rule,
+};
+
+// At most this many runs, counting the main one.
+pub const MAX_RUNS: usize = 4;
+
+// This must match the value of the workgroup_size() annotation in difference.wgsl
+const WORKGROUP_SIZE: (u32, u32) = (8, 8);
+
+// What's different about one of the other runs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Variant {
+    Rule(Rule),
+    Threshold(f32),
+}
+
+impl FromStr for Variant {
+    type Err = String;
+
+    // A number is a threshold; anything else has to be a rule.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse() {
+            Ok(threshold) => Ok(Variant::Threshold(threshold)),
+            Err(_) => s.parse().map(Variant::Rule),
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Variant::Rule(rule) => write!(f, "{}", rule),
+            Variant::Threshold(threshold) => write!(f, "{}", threshold),
+        }
+    }
+}
+
+// The main run's state, which the others start from.
+pub struct Main<'a> {
+    pub life: &'a Life,
+    pub texture: &'a Texture,
+    pub rule: Rule,
+    pub threshold: f32,
+    pub trail: &'a dyn Bindable,
+    pub clip: &'a dyn Bindable,
+    pub colormap: &'a Colormap,
+}
+
+// ---------------------------------------------------------------------------
+
+struct Run {
+    variant: Variant,
+    rule: Rule,
+    threshold: f32,
+    params: Buffer,
+    texture: Texture,
+    life: Life,
+    renderer: Renderer,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct Thresholds {
+    a: f32,
+    b: f32,
+    _pad: [f32; 2],
+}
+
+// Where the main run and another one disagree.
+struct Difference {
+    thresholds: Buffer,     // of the two runs
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    renderer: Renderer,
+    dim: Dimensions,
+}
+
+impl Difference {
+    // The renderer sees cells that are alive in both runs at the bottom of
+    // the colormap, and ones in only one run at either end of the top.
+    // These go with the values in difference.wgsl.
+    fn colormap() -> Colormap {
+        Colormap::from_stops("difference", &[
+            [0x60, 0x60, 0x60],
+            [0xff, 0x80, 0x00],
+            [0xff, 0x80, 0x00],
+            [0x00, 0xc0, 0xff],
+            [0x00, 0xc0, 0xff],
+        ])
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        dim: Dimensions,
+        params: &dyn Fn(Rule, f32) -> Buffer,
+        main: &Main,
+        other: &Run,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("difference"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    include_str!("difference.wgsl"))),
+            });
+        let thresholds = Buffer::new_init(device, "difference thresholds",
+            BufferType::Uniform, bytemuck::bytes_of(&Thresholds {
+                a: main.threshold,
+                b: other.threshold,
+                _pad: [0.0; 2],
+            }));
+        // The renderer gets the difference, which is alive above 0.5.
+        // This is kept alive by the bind groups that use it.
+        let params = params(main.rule, 0.5);
+        let texture = Texture::new(device, dim, wgpu::TextureFormat::R32Float);
+
+        let args: Vec<(_, &dyn Bindable)> = vec![
+            (BindAccess::ReadOnly,    &params),
+            (BindAccess::ReadOnly,    &thresholds),
+            (BindAccess::ReadSampled, main.texture),
+            (BindAccess::ReadSampled, &other.texture),
+            (BindAccess::WriteOnly,   &texture),
+        ];
+        let (pipeline, bind_group) = Binder::bind_up(device, &shader, "main", &args);
+
+        let renderer = Renderer::new(format, device, &params, &texture,
+            main.trail, main.life.ages(), main.clip);
+        renderer.set_colormap(queue, &Difference::colormap());
+
+        Difference {
+            thresholds,
+            pipeline,
+            bind_group,
+            renderer,
+            dim,
+        }
+    }
+
+    fn step(
+        &self,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let xgroups = (self.dim.width() + WORKGROUP_SIZE.0 - 1) / WORKGROUP_SIZE.0;
+        let ygroups = (self.dim.height() + WORKGROUP_SIZE.1 - 1) / WORKGROUP_SIZE.1;
+        let mut cpass = command_encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("difference")
+            });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch(xgroups, ygroups, 1);
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct Compare {
+    runs: Vec<Run>,
+    difference: Option<Difference>,
+    pub show_difference: bool,
+}
+
+impl Compare {
+    // Start the other runs off from where the main one is now.
+    // The params function makes Life parameters for the grid.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        dim: Dimensions,
+        params: &dyn Fn(Rule, f32) -> Buffer,
+        main: &Main,
+        variants: &[Variant],
+    ) -> Self {
+        // The random state is copied from the main run.
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(0);
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("compare")
+            });
+        let runs: Vec<Run> = variants.iter().take(MAX_RUNS - 1).map(|&variant| {
+            let (rule, threshold) = match variant {
+                Variant::Rule(rule) => (rule, main.threshold),
+                Variant::Threshold(threshold) => (main.rule, threshold),
+            };
+            let params = params(rule, threshold);
+            let texture = Texture::new(device, dim, wgpu::TextureFormat::R32Float);
+            let mut life = Life::new(device, dim, &params, &texture, &mut rng);
+            life.copy_from(&mut command_encoder, main.life);
+            let renderer = Renderer::new(format, device, &params, &texture,
+                main.trail, life.ages(), main.clip);
+            renderer.set_colormap(queue, main.colormap);
+            Run {
+                variant,
+                rule,
+                threshold,
+                params,
+                texture,
+                life,
+                renderer,
+            }
+        }).collect();
+        queue.submit(Some(command_encoder.finish()));
+
+        let difference = runs.first().map(|other| {
+            Difference::new(device, queue, format, dim, params, main, other)
+        });
+        log::info!("compare: {}", runs.iter()
+            .map(|run| run.variant.to_string()).collect::<Vec<_>>().join(", "));
+
+        Compare {
+            runs,
+            difference,
+            show_difference: false,
+        }
+    }
+
+    pub fn variants(&self) -> Vec<Variant> {
+        self.runs.iter().map(|run| run.variant).collect()
+    }
+
+    // Make the other runs the same as the main one again.
+    pub fn sync(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        main: &Life,
+    ) {
+        for run in &mut self.runs {
+            run.life.copy_from(command_encoder, main);
+        }
+    }
+
+    // Step the other runs, after the main one has been stepped.
+    pub fn step(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        for run in &mut self.runs {
+            run.life.step(command_encoder);
+        }
+    }
+
+    // The rule of the main run has changed.  The runs with a different
+    // threshold take it on, and the ones with a different rule keep theirs.
+    // The write_params function writes Life parameters into a run's buffer.
+    pub fn set_rule(
+        &mut self,
+        rule: Rule,
+        write_params: &dyn Fn(&Buffer, Rule, f32),
+    ) {
+        for run in &mut self.runs {
+            if let Variant::Threshold(_) = run.variant {
+                run.rule = rule;
+                write_params(&run.params, run.rule, run.threshold);
+            }
+        }
+    }
+
+    // The threshold of the main run has changed.  The runs with a different
+    // rule take it on, and the ones with a different threshold keep theirs.
+    pub fn set_threshold(
+        &mut self,
+        queue: &wgpu::Queue,
+        threshold: f32,
+        write_params: &dyn Fn(&Buffer, Rule, f32),
+    ) {
+        for run in &mut self.runs {
+            if let Variant::Rule(_) = run.variant {
+                run.threshold = threshold;
+                write_params(&run.params, run.rule, run.threshold);
+            }
+        }
+        if let (Some(difference), Some(other)) = (&self.difference, self.runs.first()) {
+            queue.write_buffer(difference.thresholds.buf(), 0, bytemuck::bytes_of(
+                &Thresholds {
+                    a: threshold,
+                    b: other.threshold,
+                    _pad: [0.0; 2],
+                }));
+        }
+    }
+
+    // The other runs are colored the same way as the main one.
+    pub fn set_colormap(
+        &self,
+        queue: &wgpu::Queue,
+        colormap: &Colormap,
+    ) {
+        for run in &self.runs {
+            run.renderer.set_colormap(queue, colormap);
+        }
+    }
+
+    fn panes(&self) -> usize {
+        1 + self.runs.len() + self.showing_difference() as usize
+    }
+
+    fn showing_difference(&self) -> bool {
+        self.show_difference && self.difference.is_some()
+    }
+
+    // How many panes there are across and down.
+    fn layout(&self) -> (u32, u32) {
+        let n = self.panes() as u32;
+        let across = (n as f32).sqrt().ceil() as u32;
+        (across, (n + across - 1) / across)
+    }
+
+    // The size of each pane in the window.
+    pub fn pane_size(
+        &self,
+        window: Dimensions,
+    ) -> Dimensions {
+        let (across, down) = self.layout();
+        Dimensions::new((window.width() / across).max(1),
+            (window.height() / down).max(1))
+    }
+
+    fn pane(
+        &self,
+        i: usize,
+        window: Dimensions,
+    ) -> Rect {
+        let (across, _) = self.layout();
+        let size = self.pane_size(window);
+        let (col, row) = (i as u32 % across, i as u32 / across);
+        Rect::new((col * size.width()) as i32, (row * size.height()) as i32,
+            size.width(), size.height())
+    }
+
+    // Where a position in the window is within its pane.  The mouse does
+    // the same thing in any pane as it would in the main one.
+    pub fn pane_position(
+        &self,
+        window: Dimensions,
+        (x, y): (f64, f64),
+    ) -> (f64, f64) {
+        let size = self.pane_size(window);
+        (x.rem_euclid(size.width() as f64), y.rem_euclid(size.height() as f64))
+    }
+
+    // Draw all of the panes, into a view of the given size.  The main
+    // renderer's camera and overlay should already be set; the others get
+    // the same camera, with their own overlays.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        dim: Dimensions,
+        main: &mut Renderer,
+        camera: &Camera,
+        overlay: &Overlay,
+    ) {
+        if let (true, Some(difference)) = (self.show_difference, &self.difference) {
+            difference.step(command_encoder);
+        }
+
+        // The panes might not cover all of the window.
+        command_encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("compare"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+
+        main.render_viewport(command_encoder, view, self.pane(0, dim));
+        let panes: Vec<Rect> = (1..self.panes()).map(|i| self.pane(i, dim)).collect();
+        let mut panes = panes.into_iter();
+        for run in &mut self.runs {
+            run.renderer.set_camera(queue, camera);
+            run.renderer.set_overlay(queue, overlay);
+            if let Some(pane) = panes.next() {
+                run.renderer.render_viewport(command_encoder, view, pane);
+            }
+        }
+        if let (true, Some(difference)) = (self.show_difference, &mut self.difference) {
+            // Only the grid lines make sense over the difference.
+            let mut plain = Overlay::default();
+            if overlay.has_grid_lines() {
+                plain.grid_lines(camera.cell_size());
+            }
+            difference.renderer.set_camera(queue, camera);
+            difference.renderer.set_overlay(queue, &plain);
+            if let Some(pane) = panes.next() {
+                difference.renderer.render_viewport(command_encoder, view, pane);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn variants() {
+        assert_eq!("0.4".parse(), Ok(Variant::Threshold(0.4)));
+        assert_eq!("B36/S23".parse(), Ok(Variant::Rule("B36/S23".parse().unwrap())));
+        assert!("nonsense".parse::<Variant>().is_err());
+    }
+}
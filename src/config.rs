@@ -0,0 +1,91 @@
+// Settings that are read from a TOML config file at startup.
+
+use std::collections::BTreeMap;
+#[cfg(not(target_arch = "wasm32"))]
+use toml::{value::Table, Value};
+
+use crate::{
+    bindings::{Action, Keys},
+    post::Effect,
+    window::{FrameRate, PresentMode},
+};
+
+#[derive(Debug, Default)]
+pub struct Config {
+    pub bindings: BTreeMap<Action, Keys>,
+    pub post: Vec<Effect>,      // post-processing, in the order it's done
+    pub present_mode: Option<PresentMode>,
+    pub frame_rate: Option<FrameRate>,
+}
+
+impl Config {
+    // Read the config file.  It's fine for it not to exist, but
+    // if it does exist and can't be used, that's worth a warning.
+    // A setting that's unknown or wrong is left out, with a warning,
+    // and the rest of the file is still used.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(
+        path: Option<&str>,
+    ) -> Config {
+        let path = match path {
+            Some(path) => path,
+            None => return Config::default(),
+        };
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Config::default(),
+        };
+        let table: Table = match toml::from_str(&text) {
+            Ok(table) => table,
+            Err(e) => {
+                log::warn!("config: {}: {}", path, e);
+                return Config::default();
+            },
+        };
+
+        let mut config = Config::default();
+        for (key, value) in table {
+            let result = match key.as_str() {
+                "bindings" => value.try_into::<Table>().map(|bindings| {
+                    config.bindings = Config::bindings(path, bindings);
+                }),
+                "post" => value.try_into().map(|post| config.post = post),
+                "present_mode" => value.try_into()
+                    .map(|mode| config.present_mode = Some(mode)),
+                "frame_rate" => value.try_into()
+                    .map(|rate| config.frame_rate = Some(rate)),
+                _ => {
+                    log::warn!("config: {}: unknown setting \"{}\"", path, key);
+                    continue;
+                },
+            };
+            if let Err(e) = result {
+                log::warn!("config: {}: {}: {}", path, key, e);
+            }
+        }
+        log::info!("config: loaded {}", path);
+        config
+    }
+
+    // The key bindings, leaving out any that can't be used.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn bindings(
+        path: &str,
+        table: Table,
+    ) -> BTreeMap<Action, Keys> {
+        table.into_iter().filter_map(|(action, keys)| {
+            let binding = Value::String(action.clone()).try_into()
+                .and_then(|action| keys.try_into().map(|keys| (action, keys)));
+            binding.map_err(|e| {
+                log::warn!("config: {}: bindings.{}: {}", path, action, e);
+            }).ok()
+        }).collect()
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load(
+        _path: Option<&str>,
+    ) -> Config {
+        Config::default()
+    }
+}
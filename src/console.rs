@@ -0,0 +1,407 @@
+// A drop-down console for typing commands.
+//
+// Anything that doesn't deserve a key of its own can be done from here,
+// like changing the rule or loading a pattern from a file.
+
+use std::collections::VecDeque;
+use winit::event::VirtualKeyCode;
+
+use crate::{
+    compare::{MAX_RUNS, Variant},
+    dimensions::Dimensions,
+    font::GLYPH_WIDTH,
+    rule::Rule,
+    text::TextOverlay,
+};
+
+// How many lines of output are kept on the screen.
+const OUTPUT_LINES: usize = 8;
+
+// How many commands are remembered for Up and Down.
+const HISTORY_LINES: usize = 100;
+
+const PROMPT: &str = "> ";
+
+// The commands, what they take, and what they do, for "help".
+const COMMANDS: &[(&str, &str, &str)] = &[
+    ("cellsize", "PIXELS", "fill the window with cells this big"),
+    ("clear", "", "kill every cell"),
+    ("colormap", "[NAME|FILE]", "list the colormaps, or choose one"),
+    ("compare", "[VARIANT...]", "run up to 3 other rules or thresholds alongside"),
+    ("goto", "[GENERATION]", "run forward to a generation, or stop doing that"),
+    ("grid", "WxH", "change the size of the grid"),
+    ("help", "", "list the commands"),
+    ("load", "FILE", "stamp a pattern from an RLE file"),
+    ("rule", "B3/S23", "change the rule"),
+    ("save", "FILE", "save the selection, or everything, as RLE"),
+    ("seed", "NUMBER", "fill the grid with random cells"),
+    ("threshold", "VALUE", "change the threshold between dead and alive"),
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    CellSize(u32),
+    Clear,
+    Colormap(Option<String>),
+    Compare(Vec<Variant>),      // none stops comparing
+    Goto(Option<usize>),        // none stops going
+    Grid(Dimensions),
+    Help,
+    Load(String),
+    Rule(Rule),
+    Save(String),
+    Seed(u64),
+    Threshold(f32),
+}
+
+impl Command {
+    pub fn parse(
+        line: &str,
+    ) -> Result<Command, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().unwrap_or("");
+
+        // This is the only one that takes more than one argument.
+        if name == "compare" {
+            let variants = words.map(str::parse).collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("compare: {}", e))?;
+            if variants.len() >= MAX_RUNS {
+                return Err(format!("compare: at most {} at a time", MAX_RUNS - 1));
+            }
+            return Ok(Command::Compare(variants));
+        }
+
+        let arg = words.next();
+        if words.next().is_some() {
+            return Err(format!("{}: too many arguments", name));
+        }
+
+        let need = |what: &str| arg
+            .ok_or_else(|| format!("{}: needs a {}", name, what));
+        let none = || match arg {
+            Some(_) => Err(format!("{}: doesn't take an argument", name)),
+            None => Ok(()),
+        };
+        let bad = |value: &str| format!("{}: bad value \"{}\"", name, value);
+
+        match name {
+            "cellsize" => need("size")
+                .and_then(|c| c.parse().ok().filter(|&c| c > 0).ok_or_else(|| bad(c)))
+                .map(Command::CellSize),
+            "clear" => none().map(|_| Command::Clear),
+            "colormap" => Ok(Command::Colormap(arg.map(str::to_string))),
+            "goto" => arg.map(|g| g.parse().map_err(|_| bad(g))).transpose()
+                .map(Command::Goto),
+            "grid" => need("size")
+                .and_then(|d| d.parse().map_err(|e| format!("grid: {}", e)))
+                .map(Command::Grid),
+            "help" => none().map(|_| Command::Help),
+            "load" => need("file name").map(|f| Command::Load(f.to_string())),
+            "rule" => need("rule")
+                .and_then(|r| r.parse().map_err(|e| format!("rule: {}", e)))
+                .map(Command::Rule),
+            "save" => need("file name").map(|f| Command::Save(f.to_string())),
+            "seed" => need("number")
+                .and_then(|s| s.parse().map_err(|_| bad(s)))
+                .map(Command::Seed),
+            "threshold" => need("value")
+                .and_then(|t| t.parse().map_err(|_| bad(t)))
+                .map(Command::Threshold),
+            _ => Err(format!("unknown command \"{}\"; try \"help\"", name)),
+        }
+    }
+
+    // A line for each command.
+    pub fn help() -> Vec<String> {
+        COMMANDS.iter().map(|(name, arg, what)| {
+            format!("{:<22} {}", format!("{} {}", name, arg), what)
+        }).collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// The longest prefix that all of the strings share.
+fn common_prefix(
+    words: &[String],
+) -> String {
+    let first = match words.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+    let mut len = first.len();
+    for word in &words[1..] {
+        len = first.char_indices().zip(word.chars())
+            .take_while(|((_, a), b)| a == b)
+            .last()
+            .map_or(0, |((i, a), _)| i + a.len_utf8())
+            .min(len);
+    }
+    first[..len].to_string()
+}
+
+// Files that could finish off a partly typed path.
+#[cfg(not(target_arch = "wasm32"))]
+fn complete_path(
+    partial: &str,
+) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind('/') {
+        Some(i) => (&partial[..=i], &partial[(i + 1)..]),
+        None => ("", partial),
+    };
+    let entries = match std::fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().into_string().ok()?;
+            let slash = if e.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, slash))
+        })
+        .filter(|path| path[dir.len()..].starts_with(prefix))
+        .collect();
+    paths.sort();
+    paths
+}
+
+// (There aren't any files under wasm32.)
+#[cfg(target_arch = "wasm32")]
+fn complete_path(
+    _partial: &str,
+) -> Vec<String> {
+    Vec::new()
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct Console {
+    open: bool,
+    input: String,
+    output: VecDeque<String>,
+    history: Vec<String>,
+    recalled: Option<usize>,    // which history line is being edited
+    text: TextOverlay,
+}
+
+impl Console {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        Console {
+            open: false,
+            input: String::new(),
+            output: VecDeque::new(),
+            history: Vec::new(),
+            recalled: None,
+            text: TextOverlay::new(device, queue, format),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+    }
+
+    // Show a line of output.
+    pub fn print(
+        &mut self,
+        line: impl Into<String>,
+    ) {
+        self.output.push_back(line.into());
+        while self.output.len() > OUTPUT_LINES {
+            self.output.pop_front();
+        }
+    }
+
+    // Replace the last line of output, for something that's still going.
+    pub fn reprint(
+        &mut self,
+        line: impl Into<String>,
+    ) {
+        self.output.pop_back();
+        self.print(line);
+    }
+
+    // A character has been typed.
+    pub fn type_char(
+        &mut self,
+        c: char,
+    ) {
+        // The backtick closes the console, so it doesn't get typed.
+        if self.open && c != '`' && !c.is_control() {
+            self.input.push(c);
+        }
+    }
+
+    // Handle a key that isn't just typing, returning the command
+    // if one was entered.
+    pub fn key(
+        &mut self,
+        keycode: VirtualKeyCode,
+    ) -> Option<Command> {
+        match keycode {
+            VirtualKeyCode::Grave | VirtualKeyCode::Escape => self.open = false,
+            VirtualKeyCode::Back => {
+                self.input.pop();
+            },
+            VirtualKeyCode::Up => self.recall(-1),
+            VirtualKeyCode::Down => self.recall(1),
+            VirtualKeyCode::Tab => self.complete(),
+            VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => {
+                return self.enter();
+            },
+            _ => {},
+        }
+        None
+    }
+
+    fn enter(&mut self) -> Option<Command> {
+        let line = std::mem::take(&mut self.input);
+        self.recalled = None;
+        if line.trim().is_empty() {
+            return None;
+        }
+        self.print(format!("{}{}", PROMPT, line));
+        if self.history.last() != Some(&line) {
+            self.history.push(line.clone());
+            if self.history.len() > HISTORY_LINES {
+                self.history.remove(0);
+            }
+        }
+        match Command::parse(&line) {
+            Ok(command) => Some(command),
+            Err(e) => {
+                self.print(e);
+                None
+            },
+        }
+    }
+
+    // Go back or forward through the commands that have been entered.
+    fn recall(
+        &mut self,
+        direction: isize,
+    ) {
+        if self.history.is_empty() {
+            return;
+        }
+        let end = self.history.len() as isize;
+        let at = self.recalled.map_or(end, |i| i as isize) + direction;
+        if at < 0 {
+            return;
+        }
+        if at >= end {
+            self.recalled = None;
+            self.input.clear();
+        } else {
+            self.recalled = Some(at as usize);
+            self.input = self.history[at as usize].clone();
+        }
+    }
+
+    // Finish off the command name, or the file name for load and save,
+    // as far as it can be done without guessing.
+    fn complete(&mut self) {
+        let (before, partial) = match self.input.rfind(' ') {
+            Some(i) => self.input.split_at(i + 1),
+            None => ("", self.input.as_str()),
+        };
+        let (before, partial) = (before.to_string(), partial.to_string());
+        let candidates: Vec<String> = match before.split_whitespace().next() {
+            None => COMMANDS.iter()
+                .map(|(name, _, _)| name.to_string())
+                .filter(|name| name.starts_with(&partial))
+                .collect(),
+            Some("load") | Some("save") | Some("colormap") => complete_path(&partial),
+            Some(_) => Vec::new(),
+        };
+
+        let completed = match candidates.as_slice() {
+            [] => return,
+            // A command or a file is done, but a directory might not be.
+            [only] if before.is_empty() || !only.ends_with('/') => {
+                format!("{} ", only)
+            },
+            _ => common_prefix(&candidates),
+        };
+        if candidates.len() > 1 && completed == partial {
+            self.print(candidates.join("  "));
+        }
+        self.input = format!("{}{}", before, completed);
+    }
+
+    // Draw the console across the top of the view, if it's open.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        window: Dimensions,
+        scale: u32,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        // Pad the lines out so that the console spans the window.
+        let cols = (window.width() / scale / GLYPH_WIDTH as u32)
+            .saturating_sub(1) as usize;
+        let mut lines: Vec<String> = self.output.iter().cloned().collect();
+        lines.push(format!("{}{}_", PROMPT, self.input));
+        let lines = lines.into_iter()
+            .map(|line| format!("{:<width$}", line, width = cols))
+            .collect();
+
+        self.text.set_text(lines);
+        self.text.update(device, queue);
+        self.text.render(queue, command_encoder, view, window, (0, 0), scale);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        assert_eq!(Command::parse("clear"), Ok(Command::Clear));
+        assert_eq!(Command::parse("  goto   500 "), Ok(Command::Goto(Some(500))));
+        assert_eq!(Command::parse("goto"), Ok(Command::Goto(None)));
+        assert_eq!(Command::parse("grid 640x480"),
+            Ok(Command::Grid(Dimensions::new(640, 480))));
+        assert_eq!(Command::parse("rule B36/S23"),
+            Ok(Command::Rule("B36/S23".parse().unwrap())));
+        assert_eq!(Command::parse("threshold 0.25"), Ok(Command::Threshold(0.25)));
+        assert_eq!(Command::parse("colormap"), Ok(Command::Colormap(None)));
+        assert_eq!(Command::parse("compare"), Ok(Command::Compare(Vec::new())));
+        assert_eq!(Command::parse("compare 0.3 B3/S23"), Ok(Command::Compare(vec![
+            Variant::Threshold(0.3),
+            Variant::Rule("B3/S23".parse().unwrap()),
+        ])));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(Command::parse("frobnicate"),
+            Err("unknown command \"frobnicate\"; try \"help\"".to_string()));
+        assert_eq!(Command::parse("clear everything"),
+            Err("clear: doesn't take an argument".to_string()));
+        assert_eq!(Command::parse("seed"), Err("seed: needs a number".to_string()));
+        assert_eq!(Command::parse("seed 1 2"), Err("seed: too many arguments".to_string()));
+        assert_eq!(Command::parse("goto soon"), Err("goto: bad value \"soon\"".to_string()));
+        assert_eq!(Command::parse("cellsize 0"), Err("cellsize: bad value \"0\"".to_string()));
+        assert!(Command::parse("rule 23/3").is_err());
+        assert!(Command::parse("grid 640").is_err());
+        assert!(Command::parse("compare 0.1 0.2 0.3 0.4").is_err());
+        assert!(Command::parse("").is_err());
+    }
+}
@@ -0,0 +1,52 @@
+// Compares two Life grids, cell by cell, for the difference view.
+// The values written out are read by renderer.wgsl with a threshold of 0.5,
+// and go with the colormap made by Difference::colormap() in compare.rs.
+
+[[block]]
+struct LifeParams {
+    width : u32;
+    height : u32;
+    threshold : f32;
+    birth : u32;
+    survive : u32;
+};
+
+[[block]]
+struct Thresholds {
+    a : f32;
+    b : f32;
+    pad0 : f32;
+    pad1 : f32;
+};
+
+[[group(0), binding(0)]] var<uniform> params: LifeParams;
+[[group(0), binding(1)]] var<uniform> thresholds: Thresholds;
+[[group(0), binding(2)]] var a: texture_2d<f32>;
+[[group(0), binding(3)]] var b: texture_2d<f32>;
+[[group(0), binding(4)]] var difference: texture_storage_2d<r32float, write>;
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    if (global_id.x >= params.width || global_id.y >= params.height) {
+        return;
+    }
+
+    let coord : vec2<i32> = vec2<i32>(i32(global_id.x), i32(global_id.y));
+    let alive_a : bool = textureLoad(a, coord, 0).x > thresholds.a;
+    let alive_b : bool = textureLoad(b, coord, 0).x > thresholds.b;
+
+    // dead in both: black; alive in both: the bottom of the colormap;
+    // alive in only one of them: its own color
+    var value : f32 = 0.0;
+    if (alive_a && alive_b) {
+        value = 0.501;
+    }
+    if (alive_a && !alive_b) {
+        value = 0.6875;
+    }
+    if (!alive_a && alive_b) {
+        value = 1.0;
+    }
+
+    textureStore(difference, coord, vec4<f32>(value, 0.0, 0.0, 1.0));
+}
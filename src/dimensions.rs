@@ -1,3 +1,5 @@
+use std::str::FromStr;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Dimensions {
     pub width: u32,
@@ -27,3 +29,54 @@ impl Dimensions {
         self.height
     }
 }
+
+// Parse something like "1280x720".
+impl FromStr for Dimensions {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("size \"{}\" isn't of the form 1280x720", text);
+        let (w, h) = text.split_once('x').ok_or_else(bad)?;
+        match (w.parse(), h.parse()) {
+            (Ok(w), Ok(h)) if w > 0 && h > 0 => Ok(Dimensions::new(w, h)),
+            _ => Err(bad()),
+        }
+    }
+}
+
+// A rectangle of cells.  The origin can be negative, or the rectangle can
+// run off the far edge, since the Life grid wraps around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn new(
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // The smallest rectangle of whole cells that covers the given
+    // floating-point bounds.
+    pub fn covering(
+        (x0, y0): (f32, f32),
+        (x1, y1): (f32, f32),
+    ) -> Self {
+        let (x0, y0) = (x0.floor() as i32, y0.floor() as i32);
+        let (x1, y1) = (x1.ceil() as i32, y1.ceil() as i32);
+        Rect::new(x0, y0, (x1 - x0).max(0) as u32, (y1 - y0).max(0) as u32)
+    }
+}
@@ -0,0 +1,169 @@
+// Exporting a range of generations as a looping animated GIF.
+//
+// Cell values are mapped directly onto the colormap's palette (see
+// colormap.rs), so the frames don't need any further color quantization.
+// Every frame after the first only contains the rectangle that changed.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::ops::Range;
+use std::path::Path;
+
+use crate::{
+    colormap::{self, Colormap},
+    dimensions::Dimensions,
+};
+
+fn io_error(e: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, e)
+}
+
+pub struct GifExporter {
+    encoder: gif::Encoder<BufWriter<File>>,
+    range: Range<usize>,    // generations to be exported
+    first: Option<usize>,   // generation, once it's been exported
+    grid: Dimensions,       // of the Life grid
+    dim: Dimensions,        // of the output image
+    scale: u32,
+    delay: u16,             // in hundredths of a second
+    prev: Option<Vec<u8>>,
+}
+
+impl GifExporter {
+    pub fn new(
+        path: &Path,
+        grid: Dimensions,
+        range: Range<usize>,
+        scale: u32,
+        delay: u16,
+        colormap: &Colormap,
+    ) -> io::Result<Self> {
+        let scale = scale.max(1);
+        let dim = Dimensions::new(grid.width() * scale, grid.height() * scale);
+        if dim.width() > u16::MAX as u32 || dim.height() > u16::MAX as u32 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                format!("{}x{} is too big for a GIF", dim.width(), dim.height())));
+        }
+
+        let palette: Vec<u8> =
+            colormap.palette().iter().flatten().copied().collect();
+        let file = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(file,
+            dim.width() as u16, dim.height() as u16, &palette)
+            .map_err(io_error)?;
+        encoder.set_repeat(gif::Repeat::Infinite).map_err(io_error)?;
+
+        log::info!("gif: exporting generations {}..{} to {}",
+            range.start, range.end, path.display());
+
+        Ok(GifExporter {
+            encoder,
+            range,
+            first: None,
+            grid,
+            dim,
+            scale,
+            delay,
+            prev: None,
+        })
+    }
+
+    // The generations that are still to be exported.
+    pub fn range(&self) -> &Range<usize> {
+        &self.range
+    }
+
+    // The generations that have been exported so far.
+    pub fn written(&self) -> Range<usize> {
+        self.first.unwrap_or(self.range.start)..self.range.start
+    }
+
+    // Add one generation to the animation.
+    pub fn add_frame(
+        &mut self,
+        frame_num: usize,
+        cells: &[f32],
+        thresh: f32,
+    ) -> io::Result<()> {
+        assert_eq!(cells.len(), self.grid.area());
+
+        let image = self.indexed(cells, thresh);
+        let (left, top, width, height) = match &self.prev {
+            None => (0, 0, self.dim.width(), self.dim.height()),
+            Some(prev) => self.changed_rect(prev, &image),
+        };
+
+        let w = self.dim.width() as usize;
+        let mut buffer = Vec::with_capacity((width * height) as usize);
+        for y in top..(top + height) {
+            let start = y as usize * w + left as usize;
+            buffer.extend_from_slice(&image[start..(start + width as usize)]);
+        }
+
+        let frame = gif::Frame {
+            left: left as u16,
+            top: top as u16,
+            width: width as u16,
+            height: height as u16,
+            delay: self.delay,
+            dispose: gif::DisposalMethod::Keep,
+            buffer: Cow::Owned(buffer),
+            ..gif::Frame::default()
+        };
+        self.encoder.write_frame(&frame).map_err(io_error)?;
+
+        self.prev = Some(image);
+        self.first.get_or_insert(frame_num);
+        self.range.start = frame_num + 1;
+        Ok(())
+    }
+
+    // Turn cell values into palette indices, scaled up and flipped so that
+    // the image is the same way up as it is on screen.
+    fn indexed(
+        &self,
+        cells: &[f32],
+        thresh: f32,
+    ) -> Vec<u8> {
+        let gw = self.grid.width() as usize;
+        let gh = self.grid.height() as usize;
+        let scale = self.scale as usize;
+
+        let mut image = Vec::with_capacity(self.dim.area());
+        for y in 0..self.dim.height() as usize {
+            let row = &cells[(gh - 1 - y / scale) * gw..][..gw];
+            for x in 0..self.dim.width() as usize {
+                image.push(colormap::index(row[x / scale], thresh));
+            }
+        }
+        image
+    }
+
+    // Find the smallest rectangle containing every pixel that changed.
+    // If nothing changed, this is a single unchanged pixel, since every
+    // generation still needs its own frame.
+    fn changed_rect(
+        &self,
+        prev: &[u8],
+        image: &[u8],
+    ) -> (u32, u32, u32, u32) {
+        let w = self.dim.width();
+        let (mut x0, mut y0, mut x1, mut y1) = (w, self.dim.height(), 0, 0);
+        for (i, (a, b)) in prev.iter().zip(image).enumerate() {
+            if a != b {
+                let (x, y) = (i as u32 % w, i as u32 / w);
+                x0 = x0.min(x);
+                y0 = y0.min(y);
+                x1 = x1.max(x + 1);
+                y1 = y1.max(y + 1);
+            }
+        }
+
+        if x0 >= x1 {
+            (0, 0, 1, 1)
+        } else {
+            (x0, y0, x1 - x0, y1 - y0)
+        }
+    }
+}
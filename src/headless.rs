@@ -0,0 +1,84 @@
+// Running an Example without a window, such as on a server or in CI.
+//
+// There's no surface to be compatible with, so any adapter will do,
+// including software ones like llvmpipe and lavapipe.  Each frame is
+// rendered into an offscreen texture instead of the window, and nothing
+// comes in from the keyboard or mouse: the example just renders the given
+// number of frames, and is then told to quit.  Anything it wants to keep,
+// like screenshots, recordings or patterns, it reads back for itself.
+
+use std::time::Instant;
+
+use pollster::block_on;
+
+use crate::{
+    capture::CaptureTarget,
+    dimensions::Dimensions,
+    window::{self, Example, Spawner},
+};
+
+// The offscreen frames are in the format that screenshots expect.
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+// Any adapter will do, with no window to draw into.
+pub async fn request_adapter(
+    instance: &wgpu::Instance,
+    backend: wgpu::Backends,
+) -> wgpu::Adapter {
+    // Failing the usual choice, take whatever there is, down to software.
+    let adapter = match wgpu::util::initialize_adapter_from_env_or_default(
+        instance, backend).await {
+        Some(adapter) => adapter,
+        None => instance.enumerate_adapters(backend).next()
+            .expect("No GPU adapters found, not even software ones!"),
+    };
+    let adapter_info = adapter.get_info();
+    log::info!("Using {} ({:?}, {:?})", adapter_info.name,
+        adapter_info.backend, adapter_info.device_type);
+    adapter
+}
+
+// Run <E> for a number of frames of the given size.
+pub fn run<E: Example>(
+    size: Dimensions,
+    frames: usize,
+) {
+    env_logger::init();
+
+    // Software renderers are often only on the secondary backends.
+    let backend = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+    let instance = wgpu::Instance::new(backend);
+    let adapter = block_on(request_adapter(&instance, backend));
+    let (device, queue) = block_on(window::request_device::<E>(&adapter));
+
+    // There's no surface to configure, but the example is told what its
+    // frames are like in the same way.
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: FORMAT,
+        width: size.width(),
+        height: size.height(),
+        present_mode: wgpu::PresentMode::Fifo,
+    };
+    let spawner = Spawner::new();
+
+    log::info!("Initializing the example (headless, {}x{})...",
+        size.width(), size.height());
+    let mut example = E::init(&config, &adapter, &device, &queue);
+    let target = CaptureTarget::new(&device, size, FORMAT);
+
+    log::info!("Rendering {} frames...", frames);
+    let start = Instant::now();
+    for _ in 0..frames {
+        example.render(target.view(), &device, &queue, &spawner);
+        spawner.run_until_stalled();
+    }
+    device.poll(wgpu::Maintain::Wait);
+
+    // (stdout may be carrying a recorded video stream)
+    let elapsed = start.elapsed().as_secs_f64();
+    eprintln!("Rendered {} frames in {:.2}s ({:.1} fps)",
+        frames, elapsed, frames as f64 / elapsed.max(f64::EPSILON));
+
+    example.quit(&device, &queue);
+}
@@ -0,0 +1,196 @@
+// A heads-up display of what the simulation is doing.
+//
+// The population is counted on the GPU by each Life step.  The count is
+// copied into a small buffer and read back whenever the previous read has
+// finished, so it never holds up a frame; it may be a frame or two behind.
+
+use std::collections::VecDeque;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::task::noop_waker_ref;
+
+use crate::{
+    dimensions::Dimensions,
+    life::Life,
+    rule::Rule,
+    text::TextOverlay,
+    window::now,
+};
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>>>>;
+
+// How far back the rates are averaged over, in seconds.
+const RATE_WINDOW: f64 = 1.0;
+
+// Space between the HUD and the corner of the window, in pixels.
+const HUD_MARGIN: u32 = 8;
+
+// What the HUD shows, apart from what it measures itself.
+pub struct HudInfo {
+    pub grid: Dimensions,
+    pub threshold: f32,
+    pub rule: Rule,
+}
+
+pub struct Hud {
+    visible: bool,
+    text: TextOverlay,
+    staging: wgpu::Buffer,
+    copied: bool,               // staging has a count on its way to it
+    mapping: Option<MapFuture>,
+    population: Option<u32>,
+    frames: VecDeque<(f64, usize)>,     // when each frame was, and its generation
+}
+
+impl Hud {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let staging = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("population readback"),
+            size: std::mem::size_of::<u32>() as _,
+            usage: wgpu::BufferUsages::MAP_READ
+                | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Hud {
+            visible: false,
+            text: TextOverlay::new(device, queue, format),
+            staging,
+            copied: false,
+            mapping: None,
+            population: None,
+            frames: VecDeque::new(),
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    // Copy out the population from the step that's just been encoded,
+    // unless the last one is still being read.
+    pub fn enqueue(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        life: &Life,
+    ) {
+        if !self.visible || self.copied || self.mapping.is_some() {
+            return;
+        }
+        command_encoder.copy_buffer_to_buffer(life.population().buf(), 0,
+            &self.staging, 0, std::mem::size_of::<u32>() as _);
+        self.copied = true;
+    }
+
+    // The commands from enqueue() have been submitted, so the count can be
+    // read once the GPU gets to them.
+    pub fn submitted(&mut self) {
+        if std::mem::take(&mut self.copied) {
+            self.mapping = Some(Box::pin(
+                self.staging.slice(..).map_async(wgpu::MapMode::Read)));
+        }
+    }
+
+    // Pick up the count if it's arrived.
+    fn poll(
+        &mut self,
+        device: &wgpu::Device,
+    ) {
+        let mapping = match &mut self.mapping {
+            Some(mapping) => mapping,
+            None => return,
+        };
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        match mapping.as_mut().poll(&mut cx) {
+            Poll::Pending => return,
+            Poll::Ready(Ok(())) => {
+                let data = self.staging.slice(..).get_mapped_range();
+                self.population = Some(*bytemuck::from_bytes::<u32>(&data));
+                drop(data);
+                self.staging.unmap();
+            },
+            Poll::Ready(Err(e)) => log::warn!("hud: couldn't read population: {}", e),
+        }
+        self.mapping = None;
+    }
+
+    // Note that a frame has been drawn, showing the given generation.
+    pub fn frame(
+        &mut self,
+        generation: usize,
+    ) {
+        let t = now();
+        self.frames.push_back((t, generation));
+        while let Some(&(t0, _)) = self.frames.front() {
+            if t - t0 <= RATE_WINDOW {
+                break;
+            }
+            self.frames.pop_front();
+        }
+    }
+
+    // Steps per second and seconds per frame, over the last little while.
+    fn rates(&self) -> Option<(f64, f64)> {
+        let &(t0, g0) = self.frames.front()?;
+        let &(t1, g1) = self.frames.back()?;
+        let dt = t1 - t0;
+        if dt <= 0.0 {
+            return None;
+        }
+        let frames = (self.frames.len() - 1) as f64;
+        Some((g1.saturating_sub(g0) as f64 / dt, dt / frames))
+    }
+
+    fn lines(
+        &self,
+        generation: usize,
+        info: &HudInfo,
+    ) -> Vec<String> {
+        let population = self.population
+            .map_or_else(|| "-".to_string(), |p| p.to_string());
+        let (steps, frame_ms) = self.rates()
+            .map_or_else(|| ("-".to_string(), "-".to_string()),
+                |(s, f)| (format!("{:.1}", s), format!("{:.2} ms", f * 1000.0)));
+        vec![
+            format!("generation {}", generation),
+            format!("population {}", population),
+            format!("steps/sec  {}", steps),
+            format!("frame time {}", frame_ms),
+            format!("grid       {}x{}", info.grid.width(), info.grid.height()),
+            format!("threshold  {:.2}", info.threshold),
+            format!("rule       {}", info.rule),
+        ]
+    }
+
+    // Draw the HUD in the top left corner of the view, if it's showing.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        window: Dimensions,
+        scale: u32,
+        generation: usize,
+        info: &HudInfo,
+    ) {
+        self.poll(device);
+        if !self.visible {
+            return;
+        }
+
+        self.text.set_text(self.lines(generation, info));
+        self.text.update(device, queue);
+        self.text.render(queue, command_encoder, view, window,
+            (HUD_MARGIN, HUD_MARGIN), scale);
+    }
+}
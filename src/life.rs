@@ -7,13 +7,22 @@ use rand::{
 };
 
 use crate::{
-    bindable::{Bindable, BindAccess, Binder, Buffer2D, BufferType},
+    bindable::{Bindable, BindAccess, Binder, Buffer, Buffer2D, BufferType},
+    brush::{Brush, BrushPass},
     buffer_copy::BufferCopier,
+    clipboard::{Clipboard, ClipboardPass},
     debug_buffer::DebugBuffer,
-    dimensions::Dimensions,
+    dimensions::{Dimensions, Rect},
     directions::{RenderDir, RenderMotion, RenderSources},
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    bindable::Readback,
+    rule::Rule,
+    snapshot::Snapshot,
+};
+
 // ---------------------------------------------------------------------------
 // Data that is shared between Rust and the compute pipeline in WGSL.
 
@@ -31,9 +40,14 @@ pub struct Life {
     dimensions: Dimensions,
     cell_buffers: RenderSources<Buffer2D<f32>>,
     random_buf: Buffer2D<[u32; 4]>,
+    ages: Buffer2D<[u32; 2]>,   // generations alive, generations since death
+    population: Buffer,         // counted by each step
+    zero: Buffer,               // for clearing the count
     cell_bc: BufferCopier<f32, f32>,
     rand_bc: BufferCopier<[u32; 4], [u32; 4]>,
     debug_buffer: DebugBuffer<f32>,
+    brush_pass: BrushPass,
+    clipboard_pass: ClipboardPass,
     frame_num: usize,
 }
 
@@ -74,6 +88,16 @@ impl Life {
             bytemuck::cast_slice(&random_data),
         );
 
+        // Allocate a buffer for how long each cell has been alive or dead.
+        // It starts out with every cell never having lived.
+        let ages: Buffer2D<[u32; 2]> = Buffer2D::new(device, "ages", dimensions);
+
+        // Allocate a counter for the live cells, and something to reset it with.
+        let population = Buffer::new(device, "population",
+            BufferType::Storage, std::mem::size_of::<u32>());
+        let zero = Buffer::new_init(device, "zero",
+            BufferType::Storage, bytemuck::bytes_of(&0u32));
+
         // Allocate a buffer for looking at current algorithm state.
         let debug_buffer = DebugBuffer::new(&device, dimensions);
 
@@ -82,19 +106,15 @@ impl Life {
         let rand_bc: BufferCopier<[u32; 4], [u32; 4]> = BufferCopier::new(device);
 
         // Create bind groups for the arguments.
-        let (pipeline, bind_groups) = Binder::bind_up_dir(
-            device, &shader, "life",
-            &|dir| {
-                let v: Vec<(_, &dyn Bindable)> = vec![
-                    (BindAccess::ReadOnly,  params),
-                    (BindAccess::ReadOnly,  cell_buffers.src(dir)),
-                    (BindAccess::WriteOnly, cell_buffers.dst(dir)),
-                    (BindAccess::WriteOnly, &random_buf),
-                    (BindAccess::WriteOnly, texture),
-                ];
-                v
-            }
-        );
+        let (pipeline, bind_groups) = Life::bind_up(device, &shader,
+            params, &cell_buffers, &random_buf, texture, &population, &ages);
+
+        // Set up the kernel for painting cells with the mouse.
+        let mut brush_pass = BrushPass::new(device);
+        brush_pass.bind(device, &cell_buffers);
+
+        // And the kernels for copying and pasting.
+        let clipboard_pass = ClipboardPass::new(device);
 
         Life {
             shader,
@@ -103,14 +123,47 @@ impl Life {
             dimensions,
             cell_buffers,
             random_buf,
+            ages,
+            population,
+            zero,
             cell_bc,
             rand_bc,
             debug_buffer,
+            brush_pass,
+            clipboard_pass,
             frame_num: 0,
         }
     }
 
-    // called on WindowEvent::Resized events
+    #[allow(clippy::too_many_arguments)]
+    fn bind_up(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        params: &impl Bindable,
+        cell_buffers: &RenderSources<Buffer2D<f32>>,
+        random_buf: &Buffer2D<[u32; 4]>,
+        texture: &impl Bindable,
+        population: &Buffer,
+        ages: &Buffer2D<[u32; 2]>,
+    ) -> (wgpu::ComputePipeline, RenderMotion<wgpu::BindGroup>) {
+        Binder::bind_up_dir(
+            device, shader, "life",
+            &|dir| {
+                let v: Vec<(_, &dyn Bindable)> = vec![
+                    (BindAccess::ReadOnly,  params),
+                    (BindAccess::ReadOnly,  cell_buffers.src(dir)),
+                    (BindAccess::WriteOnly, cell_buffers.dst(dir)),
+                    (BindAccess::WriteOnly, random_buf),
+                    (BindAccess::WriteOnly, texture),
+                    (BindAccess::WriteOnly, population),
+                    (BindAccess::WriteOnly, ages),
+                ];
+                v
+            }
+        )
+    }
+
+    // Change the size of the grid, keeping whatever cells still fit.
     pub fn resize(
         &mut self,
         device: &wgpu::Device,
@@ -134,26 +187,21 @@ impl Life {
             Buffer2D::new(device, "random data", dimensions);
         self.rand_bc.copy(device, queue, &self.random_buf, &random_buf);
 
+        // The ages start again from scratch.
+        let ages: Buffer2D<[u32; 2]> = Buffer2D::new(device, "ages", dimensions);
+
         // Bind up the new arguments.
-        let (pipeline, bind_groups) = Binder::bind_up_dir(
-            device, &self.shader, "life",
-            &|dir| {
-                let v: Vec<(_, &dyn Bindable)> = vec![
-                    (BindAccess::ReadOnly,  params),
-                    (BindAccess::ReadOnly,  cell_buffers.src(dir)),
-                    (BindAccess::WriteOnly, cell_buffers.dst(dir)),
-                    (BindAccess::WriteOnly, &random_buf),
-                    (BindAccess::WriteOnly, texture),
-                ];
-                v
-            }
-        );
+        let (pipeline, bind_groups) = Life::bind_up(device, &self.shader,
+            params, &cell_buffers, &random_buf, texture, &self.population,
+            &ages);
+        self.brush_pass.bind(device, &cell_buffers);
 
         self.pipeline = pipeline;
         self.bind_groups = bind_groups;
         self.dimensions = dimensions;
         self.cell_buffers = cell_buffers;
         self.random_buf = random_buf;
+        self.ages = ages;
         self.debug_buffer = DebugBuffer::new(&device, dimensions);
     }
 
@@ -173,6 +221,10 @@ impl Life {
         let ydim = self.dimensions.height() + WORKGROUP_SIZE.1 - 1;
         let ygroups = ydim / WORKGROUP_SIZE.1;
 
+        // The step counts the live cells as it goes.
+        command_encoder.copy_buffer_to_buffer(self.zero.buf(), 0,
+            self.population.buf(), 0, std::mem::size_of::<u32>() as _);
+
         let mut cpass = command_encoder.begin_compute_pass(
             &wgpu::ComputePassDescriptor {
                 label: Some("Life grid step")
@@ -183,6 +235,157 @@ impl Life {
         self.frame_num += 1;
     }
 
+    // Capture the complete state of the Life grid.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn snapshot(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rule: Rule,
+        threshold: f32,
+    ) -> Snapshot {
+        Snapshot {
+            dim: self.dimensions,
+            frame_num: self.frame_num,
+            rule,
+            threshold,
+            cells: self.src_buf().copyout_vec(device, queue),
+            rand_state: self.random_buf.copyout_vec(device, queue),
+            ages: self.ages.copyout_vec(device, queue),
+        }
+    }
+
+    // Replace the state of the Life grid with the contents of a snapshot.
+    // The params and texture must match the snapshot's dimensions.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn restore(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        snapshot: &Snapshot,
+        params: &impl Bindable,
+        texture: &impl Bindable,
+    ) {
+        let dimensions = snapshot.dim;
+        if dimensions != self.dimensions {
+            self.cell_buffers = RenderSources::new(|dir| {
+                let label = format!("Source for {:?}", dir);
+                Buffer2D::new(device, &label, dimensions)
+            });
+            self.random_buf = Buffer2D::new(device, "random data", dimensions);
+            self.ages = Buffer2D::new(device, "ages", dimensions);
+            self.debug_buffer = DebugBuffer::new(&device, dimensions);
+            self.brush_pass.bind(device, &self.cell_buffers);
+            self.dimensions = dimensions;
+        }
+
+        // The frame number determines which buffer is the source.
+        self.frame_num = snapshot.frame_num;
+        self.src_buf().copyin_vec(device, queue, &snapshot.cells);
+        self.random_buf.copyin_vec(device, queue, &snapshot.rand_state);
+        self.ages.copyin_vec(device, queue, &snapshot.ages);
+
+        let (pipeline, bind_groups) = Life::bind_up(device, &self.shader,
+            params, &self.cell_buffers, &self.random_buf, texture,
+            &self.population, &self.ages);
+        self.pipeline = pipeline;
+        self.bind_groups = bind_groups;
+    }
+
+    // Make this grid the same as another one of the same size, such as to
+    // start a comparison from the same cells.
+    pub fn copy_from(
+        &mut self,
+        command_encoder: &mut wgpu::CommandEncoder,
+        other: &Life,
+    ) {
+        assert_eq!(self.dimensions, other.dimensions);
+
+        // The frame number determines which buffer is the source.
+        self.frame_num = other.frame_num;
+        let area = self.dimensions.area();
+        command_encoder.copy_buffer_to_buffer(other.src_buf().buf(), 0,
+            self.src_buf().buf(), 0, (area * std::mem::size_of::<f32>()) as _);
+        command_encoder.copy_buffer_to_buffer(other.random_buf.buf(), 0,
+            self.random_buf.buf(), 0, (area * std::mem::size_of::<[u32; 4]>()) as _);
+        command_encoder.copy_buffer_to_buffer(other.ages.buf(), 0,
+            self.ages.buf(), 0, (area * std::mem::size_of::<[u32; 2]>()) as _);
+    }
+
+    // Paint the path that the brush has followed into the grid.
+    // This has to come before the step that should see the new cells.
+    // Returns the region that might have changed.
+    pub fn paint(
+        &self,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        brush: &mut Brush,
+    ) -> Option<Rect> {
+        self.brush_pass.apply(queue, command_encoder,
+            self.dir(), self.dimensions, brush)
+    }
+
+    // Copy a rectangle of cells out of the grid, clearing them if this
+    // is a cut.  This is submitted straight away.
+    pub fn copy_region(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rect: Rect,
+        cut: bool,
+    ) -> Buffer2D<f32> {
+        let rect = Rect::new(rect.x, rect.y,
+            rect.width.min(self.dimensions.width()),
+            rect.height.min(self.dimensions.height()));
+        self.clipboard_pass.copy(device, queue, self.src_buf(), rect, cut)
+    }
+
+    // Paste the clipboard into the grid with its bottom left corner at
+    // the given cell.  This is submitted straight away.
+    pub fn paste(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        clipboard: &Clipboard,
+        origin: (i32, i32),
+        threshold: f32,
+    ) {
+        self.clipboard_pass.paste(device, queue, self.src_buf(),
+            clipboard, origin, threshold);
+    }
+
+    // Read a rectangle of cells back from the GPU, one row after another.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_region(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rect: Rect,
+    ) -> Vec<f32> {
+        self.src_buf().read_rect(device, queue, rect)
+    }
+
+    // Likewise, but as part of some commands, without waiting for it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn enqueue_read_region(
+        &self,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+        rect: Rect,
+    ) -> Readback<f32> {
+        self.src_buf().enqueue_read_rect(device, command_encoder, rect)
+    }
+
+    // Replace a rectangle of cells, as read by read_region().
+    pub fn write_region(
+        &self,
+        queue: &wgpu::Queue,
+        rect: Rect,
+        cells: &[f32],
+    ) {
+        self.src_buf().write_rect(queue, rect, cells);
+    }
+
     // Import some data into the Life grid.
     pub fn import(
         &self,
@@ -193,6 +396,32 @@ impl Life {
         self.src_buf().copyin_vec(device, queue, cells);
     }
 
+    // Read the current contents of the Life grid back from the GPU.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn export(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> Vec<f32> {
+        self.src_buf().copyout_vec(device, queue)
+    }
+
+    // The number of live cells after the last step, as it's being
+    // counted on the GPU.  This has to be copied out to be read.
+    pub fn population(&self) -> &Buffer {
+        &self.population
+    }
+
+    // How many generations each cell has been alive for, and how many
+    // since it died, as of the last step.
+    pub fn ages(&self) -> &Buffer2D<[u32; 2]> {
+        &self.ages
+    }
+
+    pub fn dimensions(&self) -> Dimensions {
+        self.dimensions
+    }
+
     fn src_buf(&self) -> &Buffer2D<f32> {
         self.cell_buffers.src(self.dir())
     }
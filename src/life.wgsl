@@ -3,6 +3,8 @@ struct LifeParams {
     width : u32;
     height : u32;
     threshold : f32;
+    birth : u32;        // bit N set: born with N live neighbors
+    survive : u32;      // bit N set: survives with N live neighbors
 };
 
 [[block]]
@@ -15,11 +17,23 @@ struct RandState {
     state : array<vec4<u32>>;
 };
 
+[[block]]
+struct Ages {
+    ages : array<vec2<u32>>;    // generations alive, generations since death
+};
+
+[[block]]
+struct Population {
+    count : atomic<u32>;    // live cells in the new generation
+};
+
 [[group(0), binding(0)]] var<uniform> params: LifeParams;
 [[group(0), binding(1)]] var<storage, read> cellSrc: Cells;
 [[group(0), binding(2)]] var<storage, read_write> cellDst: Cells;
 [[group(0), binding(3)]] var<storage, read_write> randState: RandState;
 [[group(0), binding(4)]] var texture: texture_storage_2d<r32float, write>;
+[[group(0), binding(5)]] var<storage, read_write> population: Population;
+[[group(0), binding(6)]] var<storage, read_write> ages: Ages;
 
 fn LCGStep(z: u32, A: u32, C: u32) -> u32 {
     return (A * z + C);
@@ -51,35 +65,39 @@ fn life([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
     let H : u32 = params.height;
     let thresh : f32 = params.threshold;
 
-    if (X > W || Y > H) {
+    if (X >= W || Y >= H) {
         return;
     }
 
-    var count : i32 = 0;
+    let pix : u32 = Y * W + X;
+    let ov : f32 = cellSrc.cells[pix];
+    let was_alive : bool = ov > thresh;
+    var nv : f32;
+
+    var count : u32 = 0u32;
     for (var y : i32 = i32(Y - 1u32); y <= i32(Y + 1u32); y = y + 1) {
         for (var x : i32 = i32(X - 1u32); x <= i32(X + 1u32); x = x + 1) {
             let yw : u32 = u32(y + i32(H)) % H;
             let xw : u32 = u32(x + i32(W)) % W;
             if (cellSrc.cells[yw * W + xw] > thresh) {
-                count = count + 1;
+                count = count + 1u32;
             }
         }
     }
 
-    let pix : u32 = Y * W + X;
-    let ov : f32 = cellSrc.cells[pix];
-    let was_alive : bool = ov > thresh;
-    var nv : f32;
+    // the loop above counted the center cell too
+    if (was_alive) {
+        count = count - 1u32;
+    }
 
-    // in the first clause, "3 or 4" includes the center cell
-    if (was_alive && (count == 3 || count == 4)) {
+    if (was_alive && ((params.survive >> count) & 1u32) != 0u32) {
         if (ov - 0.01 > thresh) {
             nv = ov - 0.01;
         } else {
             nv = ov;
         }
     } else {
-        if (!was_alive && count == 3) {
+        if (!was_alive && ((params.birth >> count) & 1u32) != 0u32) {
             nv = 1.0;
         } else {
             nv = thresh; // generate_random(pix) * thresh;
@@ -87,6 +105,31 @@ fn life([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
     }
 
     cellDst.cells[pix] = nv;
+    if (nv > thresh) {
+        atomicAdd(&population.count, 1u32);
+    }
+
+    // A cell that has never been alive stays at zero generations since death.
+    let oage : vec2<u32> = ages.ages[pix];
+    var nage : vec2<u32>;
+    if (nv > thresh) {
+        if (was_alive) {
+            nage = vec2<u32>(oage.x + 1u32, 0u32);
+        } else {
+            nage = vec2<u32>(1u32, 0u32);
+        }
+    } else {
+        if (was_alive) {
+            nage = vec2<u32>(0u32, 1u32);
+        } else {
+            if (oage.y > 0u32) {
+                nage = vec2<u32>(0u32, oage.y + 1u32);
+            } else {
+                nage = vec2<u32>(0u32, 0u32);
+            }
+        }
+    }
+    ages.ages[pix] = nage;
 
     let coord : vec2<i32> = vec2<i32>(i32(X), i32(Y));
     // all channels other than the first are ignored
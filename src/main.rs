@@ -1,10 +1,42 @@
 mod bindable;
+mod bindings;
+mod brush;
 mod buffer_copy;
+mod camera;
+mod capture;
+mod clipboard;
+mod colormap;
+mod compare;
+mod config;
+mod console;
 mod debug_buffer;
 mod dimensions;
 mod directions;
+mod font;
+#[cfg(not(target_arch = "wasm32"))]
+mod gif_export;
+#[cfg(not(target_arch = "wasm32"))]
+mod headless;
+mod hud;
 mod life;
+mod options;
+mod patterns;
+mod post;
+#[cfg(not(target_arch = "wasm32"))]
+mod recorder;
 mod renderer;
+mod rle;
+mod rule;
+mod screenshot;
+#[cfg(not(target_arch = "wasm32"))]
+mod snapshot;
+#[cfg(not(target_arch = "wasm32"))]
+mod terminal;
+mod terrain;
+mod text;
+mod touch;
+mod trail;
+mod undo;
 mod window;
 
 use rand::{
@@ -12,17 +44,50 @@ use rand::{
     SeedableRng,
 };
 
-use winit::event::VirtualKeyCode;
+use winit::event::{
+    ElementState, ModifiersState, MouseButton, MouseScrollDelta,
+    VirtualKeyCode, WindowEvent,
+};
 use bytemuck::{Pod, Zeroable};
 
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
 use crate::{
     bindable::{Buffer, BufferType, Texture},
-    dimensions::Dimensions,
+    bindings::{Action, Bindings},
+    brush::Brush,
+    camera::Camera,
+    clipboard::Clipboard,
+    colormap::{Colormap, Colormaps},
+    compare::{self, Compare, Variant},
+    config::Config,
+    console::{Command, Console},
+    dimensions::{Dimensions, Rect},
+    hud::{Hud, HudInfo},
     life::Life,
-    renderer::Renderer,
+    options::Options,
+    patterns::Library,
+    post::PostChain,
+    renderer::{Overlay, Renderer},
+    rule::Rule,
+    screenshot::Screenshot,
+    text::TextOverlay,
+    terrain::Terrain,
+    trail::Trail,
+    undo::History,
     window::WindowOps,
 };
 
+#[cfg(not(target_arch = "wasm32"))]
+use crate::{
+    gif_export::GifExporter,
+    recorder::Recorder,
+    rle::Pattern,
+    snapshot::Snapshot,
+};
+
 // ---------------------------------------------------------------------------
 // Structures that are shared between Rust and the compute/fragment shaders.
 
@@ -32,33 +97,1137 @@ struct Params {
     width : u32,
     height : u32,
     threshold : f32,
+    birth : u32,
+    survive : u32,
 }
 
+// How much bigger than the window a supersampled screenshot is.
+const SCREENSHOT_SUPERSAMPLE: u32 = 4;
+
+// How far the arrow keys move the view, as a fraction of the window.
+const PAN_STEP: f64 = 0.1;
+
+// How much one click of the scroll wheel zooms by.
+const ZOOM_STEP: f32 = 1.25;
+
+// How many generations go into a GIF that's started from the keyboard,
+// if the command line didn't say.
+const GIF_LENGTH: usize = 100;
+
+// How tall the window is for each step up in the size of text.
+const TEXT_SCALE_HEIGHT: u32 = 720;
+
+// How much memory the undo history can use.
+const UNDO_MEMORY: usize = 64 << 20;
+
+// How many generations "goto" runs in each frame.
+const GOTO_BATCH: usize = 1000;
+
 // ---------------------------------------------------------------------------
 
+#[derive(Clone, Copy)]
+enum SnapshotOp {
+    Save,
+    Load,
+}
+
+#[derive(Clone, Copy)]
+enum HistoryOp {
+    Undo,
+    Redo,
+}
+
+// What the left mouse button does.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Tool {
+    Draw,
+    Select,
+    Paste,
+}
+
+#[derive(Clone, Copy)]
+enum ClipOp {
+    Copy,
+    Cut,
+    Paste((i32, i32)),
+    Pattern,            // load the selected pattern from the library
+}
+
+// The rectangle of whole cells with corners in the cells at a and b.
+fn selection_rect(
+    a: (f32, f32),
+    b: (f32, f32),
+) -> Rect {
+    Rect::covering(
+        (a.0.min(b.0).floor(), a.1.min(b.1).floor()),
+        (a.0.max(b.0).floor() + 1.0, a.1.max(b.1).floor() + 1.0),
+    )
+}
+
 /// This holds all of the state used by the program.
 struct LifeProg {
-    dim: Dimensions, // XXX for debugging
+    dim: Dimensions,            // of the Life grid
+    window: Dimensions,
+    rule: Rule,
+    threshold: f32,
+    params: Buffer,
+    texture: Texture,
     life: Life,
     renderer: Renderer,
+    post: PostChain,
+    compare: Option<Compare>,
+    terrain: Terrain,
+    show_terrain: bool,         // instead of the flat view
+    colormaps: Colormaps,
+    trail: Trail,
+    trails: bool,
+    age_colors: bool,
+    show_changes: bool,
+    options: Options,
+    bindings: Bindings,
+    help: bool,
+    text: TextOverlay,
+    hud: Hud,
+    console: Console,
+    command: Option<Command>,
+    brush: Brush,
+    camera: Camera,
+    grid_lines: bool,
+    panning: bool,
+    cursor: (f64, f64),         // in physical pixels
+    tool: Tool,
+    select_start: Option<(f32, f32)>,
+    selection: Option<Rect>,
+    clipboard: Clipboard,
+    clip_op: Option<ClipOp>,
+    patterns: Library,
+    pattern_menu: bool,         // whether keys go to the pattern menu
+    history: History,
+    history_op: Option<HistoryOp>,
+    snapshot_op: Option<SnapshotOp>,
+    screenshot: Option<u32>,    // scale of a requested screenshot
+    recording_toggled: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    recorder: Option<Recorder>,
+    gif_requested: Option<Range<usize>>,
+    goto: Option<usize>,        // the generation being run forward to
+    #[cfg(not(target_arch = "wasm32"))]
+    gif: Option<GifExporter>,
 }
 
 impl LifeProg {
+    // How big the view of the grid is: the window, or one of the panes
+    // when comparing runs.
+    fn view_dim(&self) -> Dimensions {
+        self.compare.as_ref()
+            .map_or(self.window, |compare| compare.pane_size(self.window))
+    }
+
+    // Where the mouse is, in whichever pane it's over.
+    fn pane_cursor(&self) -> (f64, f64) {
+        self.compare.as_ref().map_or(self.cursor,
+            |compare| compare.pane_position(self.window, self.cursor))
+    }
+
+    // Where the mouse is, in grid coordinates.
+    fn cursor_cell(&self) -> (f32, f32) {
+        let (u, v) = self.camera.screen_to_grid(self.pane_cursor());
+        (u * self.dim.width() as f32, v * self.dim.height() as f32)
+    }
+
+    // Whether the mouse is over the grid, rather than the bars around it.
+    fn cursor_on_grid(&self) -> bool {
+        self.camera.on_grid(self.pane_cursor())
+    }
+
+    // Where the clipboard would be pasted: centered on the mouse.
+    fn paste_origin(&self) -> (i32, i32) {
+        let (x, y) = self.cursor_cell();
+        let dim = self.clipboard.pasted_dim();
+        (x.floor() as i32 - dim.width() as i32 / 2,
+         y.floor() as i32 - dim.height() as i32 / 2)
+    }
+
+    // What's drawn over the cells of every run.
+    fn pane_overlay(&self) -> Overlay {
+        let mut overlay = Overlay::default();
+        if self.grid_lines {
+            overlay.grid_lines(self.camera.cell_size());
+        }
+        if self.age_colors {
+            overlay.color_by_age();
+        }
+        if self.show_changes {
+            overlay.changes();
+        }
+        overlay
+    }
+
+    // The selection, the paste ghost and the trail are only for the main run.
+    fn overlay(&self) -> Overlay {
+        let mut overlay = self.pane_overlay();
+        if let Some(rect) = self.selection {
+            overlay.select(rect);
+        }
+        if self.tool == Tool::Paste {
+            overlay.ghost(self.paste_origin(), self.clipboard.buf().dim(),
+                self.clipboard.orientation.bits());
+        }
+        if self.trails {
+            overlay.trail();
+        }
+        overlay
+    }
+
+    fn clipboard_op(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        op: ClipOp,
+    ) {
+        match op {
+            ClipOp::Copy | ClipOp::Cut => {
+                let rect = match self.selection {
+                    Some(rect) => rect,
+                    None => {
+                        log::warn!("nothing is selected");
+                        return;
+                    }
+                };
+                let cut = matches!(op, ClipOp::Cut);
+                let buf = if cut {
+                    self.edit(device, queue, rect,
+                        |prog| prog.life.copy_region(device, queue, rect, true))
+                } else {
+                    self.life.copy_region(device, queue, rect, false)
+                };
+                self.clipboard.set(buf);
+                self.renderer.rebind(device, &self.params, &self.texture,
+                    self.trail.texture(), self.life.ages(), self.clipboard.buf());
+                log::info!("{} {}x{} cells", if cut { "cut" } else { "copied" },
+                    rect.width, rect.height);
+            },
+            ClipOp::Paste(origin) => {
+                let dim = self.clipboard.pasted_dim();
+                let rect = Rect::new(origin.0, origin.1,
+                    dim.width(), dim.height());
+                self.edit(device, queue, rect, |prog| {
+                    prog.life.paste(device, queue, &prog.clipboard, origin,
+                        prog.threshold)
+                });
+            },
+            ClipOp::Pattern => {
+                let pattern = self.patterns.current();
+                self.clipboard.load(device, queue, pattern.dim, &pattern.cells);
+                self.renderer.rebind(device, &self.params, &self.texture,
+                    self.trail.texture(), self.life.ages(), self.clipboard.buf());
+                self.tool = Tool::Paste;
+                log::info!("stamping {}", pattern.name);
+            },
+        }
+    }
+
+    // Make a change to a rectangle of cells, remembering what was there
+    // before and after so that it can be undone.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn edit<R>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        rect: Rect,
+        change: impl FnOnce(&mut LifeProg) -> R,
+    ) -> R {
+        self.history.wait(device);
+        let before = self.life.read_region(device, queue, rect);
+        let result = change(self);
+        let after = self.life.read_region(device, queue, rect);
+        self.history.record(rect, before, after);
+        self.history.finish();
+        result
+    }
+
+    // Edits can't be read back without waiting under wasm32,
+    // so they can't be undone there.
+    #[cfg(target_arch = "wasm32")]
+    fn edit<R>(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _rect: Rect,
+        change: impl FnOnce(&mut LifeProg) -> R,
+    ) -> R {
+        change(self)
+    }
+
+    // Apply any painting that's been done with the mouse, remembering what
+    // it changed.  A whole brush stroke is undone at once.  What's under the
+    // brush is copied out before and after painting, and read back once it
+    // gets here, so that the frame doesn't wait for it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if let Some(rect) = self.brush.pending(self.life.dimensions()) {
+            let mut command_encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("paint")
+                });
+            let mut before = self.life.enqueue_read_region(device,
+                &mut command_encoder, rect);
+            self.life.paint(queue, &mut command_encoder, &mut self.brush);
+            let mut after = self.life.enqueue_read_region(device,
+                &mut command_encoder, rect);
+            queue.submit(Some(command_encoder.finish()));
+            before.map();
+            after.map();
+            self.history.record_pending(rect, before, after);
+        }
+        device.poll(wgpu::Maintain::Poll);
+        self.history.receive();
+        if !self.brush.is_painting() {
+            self.history.finish();
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn paint(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("paint")
+            });
+        self.life.paint(queue, &mut command_encoder, &mut self.brush);
+        queue.submit(Some(command_encoder.finish()));
+    }
+
+    fn undo_redo(
+        &mut self,
+        _device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        op: HistoryOp,
+    ) {
+        // A stroke that's just been painted might still be on its way.
+        #[cfg(not(target_arch = "wasm32"))]
+        self.history.wait(_device);
+
+        let patches = match op {
+            HistoryOp::Undo => self.history.undo(),
+            HistoryOp::Redo => self.history.redo(),
+        };
+        for (rect, cells) in patches {
+            self.life.write_region(queue, rect, cells);
+        }
+    }
+
+    // Do what a key binding asked for.
+    fn perform(
+        &mut self,
+        action: Action,
+    ) -> Option<WindowOps> {
+        match action {
+            Action::Quit => Some(WindowOps::Quit),
+            Action::FullScreen => Some(WindowOps::FullScreen),
+            Action::UnFullScreen => Some(WindowOps::UnFullScreen),
+            Action::Screenshot => {
+                self.screenshot = Some(1);
+                None
+            },
+            Action::SupersampledScreenshot => {
+                self.screenshot = Some(SCREENSHOT_SUPERSAMPLE);
+                None
+            },
+            Action::Record => {
+                self.recording_toggled = true;
+                None
+            },
+            Action::SaveSnapshot => {
+                self.snapshot_op = Some(SnapshotOp::Save);
+                None
+            },
+            Action::LoadSnapshot => {
+                self.snapshot_op = Some(SnapshotOp::Load);
+                None
+            },
+            Action::BrushSmaller => {
+                self.brush.set_size(self.brush.size() - 1);
+                log::info!("brush size {}", self.brush.size());
+                None
+            },
+            Action::BrushBigger => {
+                self.brush.set_size(self.brush.size() + 1);
+                log::info!("brush size {}", self.brush.size());
+                None
+            },
+            Action::BrushShape => {
+                self.brush.set_shape(self.brush.shape().next());
+                log::info!("brush shape {:?}", self.brush.shape());
+                None
+            },
+            Action::PanLeft => {
+                self.camera.pan((PAN_STEP * self.window.width() as f64, 0.0));
+                None
+            },
+            Action::PanRight => {
+                self.camera.pan((-PAN_STEP * self.window.width() as f64, 0.0));
+                None
+            },
+            Action::PanUp => {
+                self.camera.pan((0.0, PAN_STEP * self.window.height() as f64));
+                None
+            },
+            Action::PanDown => {
+                self.camera.pan((0.0, -PAN_STEP * self.window.height() as f64));
+                None
+            },
+            Action::ResetView => {
+                self.camera.reset();
+                self.terrain.reset_view();
+                None
+            },
+            Action::ExportGif => {
+                let len = self.options.gif_range.as_ref()
+                    .map_or(GIF_LENGTH, |r| r.len());
+                let start = self.life.frame_num() + 1;
+                self.gif_requested = Some(start..(start + len));
+                None
+            },
+            Action::SelectTool => {
+                self.tool = match self.tool {
+                    Tool::Select => Tool::Draw,
+                    _ => Tool::Select,
+                };
+                log::info!("tool {:?}", self.tool);
+                None
+            },
+            Action::Copy => {
+                self.clip_op = Some(ClipOp::Copy);
+                None
+            },
+            Action::Cut => {
+                self.clip_op = Some(ClipOp::Cut);
+                None
+            },
+            Action::Paste => {
+                if self.clipboard.is_empty() {
+                    log::warn!("the clipboard is empty");
+                } else {
+                    self.tool = Tool::Paste;
+                }
+                None
+            },
+            Action::Rotate => {
+                self.clipboard.orientation.rotate();
+                None
+            },
+            Action::FlipX => {
+                self.clipboard.orientation.flip_x();
+                None
+            },
+            Action::FlipY => {
+                self.clipboard.orientation.flip_y();
+                None
+            },
+            Action::Patterns => {
+                // The menu takes the place of the help while it's open.
+                self.help = false;
+                self.pattern_menu = true;
+                self.text.set_text(self.patterns.menu());
+                None
+            },
+            Action::PasteMode => {
+                self.clipboard.mode = self.clipboard.mode.next();
+                log::info!("paste mode {:?}", self.clipboard.mode);
+                None
+            },
+            Action::Help => {
+                self.help = !self.help;
+                self.text.set_text(if self.help {
+                    self.bindings.help()
+                } else {
+                    Vec::new()
+                });
+                None
+            },
+            Action::Hud => {
+                self.hud.toggle();
+                None
+            },
+            Action::Console => {
+                self.console.toggle();
+                None
+            },
+            Action::GridLines => {
+                self.grid_lines = !self.grid_lines;
+                None
+            },
+            Action::Trails => {
+                // The trail isn't kept up while it's hidden, so start afresh.
+                self.trails = !self.trails;
+                self.trail.reset();
+                None
+            },
+            Action::Difference => {
+                match &mut self.compare {
+                    Some(compare) => {
+                        compare.show_difference = !compare.show_difference;
+                        self.camera.resize(self.view_dim(), self.dim);
+                    },
+                    None => log::warn!("nothing is being compared"),
+                }
+                None
+            },
+            Action::Terrain => {
+                self.show_terrain = !self.show_terrain;
+                None
+            },
+            Action::AgeColors => {
+                self.age_colors = !self.age_colors;
+                None
+            },
+            Action::Changes => {
+                self.show_changes = !self.show_changes;
+                None
+            },
+            Action::Colormap => {
+                self.colormaps.next();
+                log::info!("colormap {}", self.colormaps.current().name);
+                None
+            },
+            Action::Undo => {
+                self.history_op = Some(HistoryOp::Undo);
+                None
+            },
+            Action::Redo => {
+                self.history_op = Some(HistoryOp::Redo);
+                None
+            },
+        }
+    }
+
+    // Carry out a command from the console.
+    fn run_command(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        command: Command,
+    ) {
+        let dim = self.life.dimensions();
+        let everything = Rect::new(0, 0, dim.width(), dim.height());
+        match command {
+            Command::Clear => {
+                let cells = vec![0.0; dim.area()];
+                self.edit(device, queue, everything,
+                    |prog| prog.life.write_region(queue, everything, &cells));
+                self.sync_compare(device, queue);
+                self.console.print("cleared");
+            },
+            Command::Colormap(None) => {
+                self.console.print(self.colormaps.names().join(" "));
+            },
+            Command::Colormap(Some(name)) => {
+                match self.colormaps.choose(&name) {
+                    Ok(()) => self.console.print(format!("colormap {}",
+                        self.colormaps.current().name)),
+                    Err(e) => self.console.print(e),
+                }
+            },
+            Command::Compare(variants) => {
+                self.start_compare(device, queue, &variants);
+                self.console.print(if variants.is_empty() {
+                    "stopped comparing".to_string()
+                } else {
+                    format!("comparing with {}", variants.iter()
+                        .map(Variant::to_string).collect::<Vec<_>>().join(", "))
+                });
+            },
+            Command::Goto(Some(generation)) => {
+                let start = self.life.frame_num();
+                if generation <= start {
+                    self.console.print(format!("already at generation {}", start));
+                    return;
+                }
+                // It gets there a batch at a time, in render(), so that the
+                // window keeps going, and it can be stopped.
+                self.goto = Some(generation);
+                self.console.print(format!("going to generation {}", generation));
+            },
+            Command::Goto(None) => {
+                match self.goto.take() {
+                    Some(_) => self.console.print(format!("stopped at generation {}",
+                        self.life.frame_num())),
+                    None => self.console.print("not going anywhere"),
+                }
+            },
+            Command::Grid(dim) => {
+                self.resize_grid(device, queue, dim);
+                self.console.print(format!("grid {}x{}", dim.width(), dim.height()));
+            },
+            Command::CellSize(size) => {
+                let dim = LifeProg::fit_grid(self.window, size);
+                self.resize_grid(device, queue, dim);
+                self.camera.reset();
+                self.console.print(format!("grid {}x{}", dim.width(), dim.height()));
+            },
+            Command::Help => {
+                for line in Command::help() {
+                    self.console.print(line);
+                }
+            },
+            Command::Load(path) => self.load_pattern(device, queue, &path),
+            Command::Rule(rule) => {
+                self.rule = rule;
+                self.write_params(queue);
+                self.console.print(format!("rule {}", rule));
+            },
+            Command::Save(path) => self.save_pattern(device, queue, &path),
+            Command::Seed(seed) => {
+                let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+                let cells: Vec<f32> = Uniform::new_inclusive(0.0, 1.0)
+                    .sample_iter(&mut rng).take(dim.area()).collect();
+                self.edit(device, queue, everything,
+                    |prog| prog.life.import(device, queue, &cells));
+                self.sync_compare(device, queue);
+                self.console.print(format!("seeded with {}", seed));
+            },
+            Command::Threshold(threshold) => {
+                self.threshold = threshold;
+                self.write_params(queue);
+                self.console.print(format!("threshold {}", threshold));
+            },
+        }
+    }
+
+    // Read a pattern from an RLE file, and start stamping it.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_pattern(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &str,
+    ) {
+        match self.patterns.load_file(Path::new(path)) {
+            Ok(pattern) => {
+                self.console.print(format!("loaded {} ({}x{})", pattern.name,
+                    pattern.dim.width(), pattern.dim.height()));
+                self.patterns.add(pattern);
+                self.clipboard_op(device, queue, ClipOp::Pattern);
+            },
+            Err(e) => self.console.print(format!("{}: {}", path, e)),
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn load_pattern(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _path: &str,
+    ) {
+        self.console.print("loading files doesn't work under wasm32");
+    }
+
+    // Write the selection, or the whole grid if nothing is selected,
+    // to an RLE file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_pattern(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &str,
+    ) {
+        match self.write_pattern(device, queue, path) {
+            Ok(rect) => self.console.print(format!("saved {}x{} cells to {}",
+                rect.width, rect.height, path)),
+            Err(e) => self.console.print(format!("{}: {}", path, e)),
+        }
+    }
+
+    // Returns the part of the grid that was written.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_pattern(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &str,
+    ) -> io::Result<Rect> {
+        let dim = self.life.dimensions();
+        let rect = self.selection
+            .unwrap_or_else(|| Rect::new(0, 0, dim.width(), dim.height()));
+        let name = Path::new(path).file_stem()
+            .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+        let pattern = Pattern {
+            name,
+            dim: Dimensions::new(rect.width, rect.height),
+            cells: self.life.read_region(device, queue, rect),
+        };
+        std::fs::write(path, pattern.to_rle(self.rule, self.threshold))?;
+        Ok(rect)
+    }
+
+    // Save whatever was asked for at the end of the run, and say how it
+    // ended up when there's been no window to watch it in.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn finish_run(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if let Some(path) = self.options.exit_screenshot.clone() {
+            if let Err(e) = Screenshot::save(device, queue, self.renderer.format(),
+                self.window, 1, Path::new(&path),
+                |encoder, view, dim| self.render_view(device, queue, encoder, view, dim)) {
+                log::warn!("couldn't save {}: {}", path, e);
+            }
+        }
+        if let Some(path) = &self.options.exit_pattern {
+            match self.write_pattern(device, queue, path) {
+                Ok(rect) => log::info!("saved {}x{} cells to {}",
+                    rect.width, rect.height, path),
+                Err(e) => log::warn!("couldn't save {}: {}", path, e),
+            }
+        }
+        if self.options.headless.is_some() {
+            let population = self.life.export(device, queue).iter()
+                .filter(|&&value| value > self.threshold)
+                .count();
+            eprintln!("generation {}, population {}, grid {}x{}, rule {}, threshold {}",
+                self.life.frame_num(), population, self.dim.width(),
+                self.dim.height(), self.rule, self.threshold);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn save_pattern(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _path: &str,
+    ) {
+        self.console.print("saving files doesn't work under wasm32");
+    }
+
+    // Keys choose from the pattern menu while it's open.
+    fn pattern_menu_key(
+        &mut self,
+        keycode: VirtualKeyCode,
+    ) {
+        match keycode {
+            VirtualKeyCode::Up => self.patterns.prev(),
+            VirtualKeyCode::Down | VirtualKeyCode::N => self.patterns.next(),
+            VirtualKeyCode::Return => {
+                self.pattern_menu = false;
+                self.text.set_text(Vec::new());
+                self.clip_op = Some(ClipOp::Pattern);
+                return;
+            },
+            VirtualKeyCode::Escape => {
+                self.pattern_menu = false;
+                self.text.set_text(Vec::new());
+                return;
+            },
+            _ => return,
+        }
+        self.text.set_text(self.patterns.menu());
+    }
+
+    // Start recording if we aren't, or stop if we are.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn toggle_recording(
+        &mut self,
+        device: &wgpu::Device,
+    ) {
+        if let Some(recorder) = &mut self.recorder {
+            if recorder.is_recording() {
+                recorder.stop(device);
+            } else {
+                recorder.start();
+            }
+            return;
+        }
+
+        // The recording keeps the size the window had when it started.
+        match Recorder::new(device, self.window, self.renderer.format(),
+            &self.options.record_path, self.options.record_format,
+            self.options.record_fps) {
+            Ok(mut recorder) => {
+                recorder.start();
+                self.recorder = Some(recorder);
+            }
+            Err(e) => {
+                log::warn!("couldn't record to {}: {}",
+                    self.options.record_path, e);
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn toggle_recording(
+        &mut self,
+        _device: &wgpu::Device,
+    ) {
+        log::warn!("recording doesn't work under wasm32");
+    }
+
+    // Add the generation that was just computed to the GIF being exported.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn export_gif(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        let frame_num = self.life.frame_num();
+        if let Some(range) = self.gif_requested.take() {
+            let path = PathBuf::from(&self.options.gif_path);
+            if range.start < frame_num {
+                // Generations that have gone by can't be gone back to.
+                log::warn!("gif: can't export generations {}..{}, since it's \
+                    already generation {}", range.start, range.end, frame_num);
+            } else {
+                match GifExporter::new(&path, self.life.dimensions(), range,
+                    self.options.gif_scale, self.options.gif_delay,
+                    self.colormaps.current()) {
+                    Ok(gif) => self.gif = Some(gif),
+                    Err(e) => log::warn!("couldn't export {}: {}",
+                        path.display(), e),
+                }
+            }
+        }
+
+        let done = match &mut self.gif {
+            None => return,
+            Some(gif) => {
+                if frame_num < gif.range().start {
+                    return;
+                }
+                let cells = self.life.export(device, queue);
+                match gif.add_frame(frame_num, &cells, self.threshold) {
+                    Ok(_) => gif.range().is_empty(),
+                    Err(e) => {
+                        log::warn!("gif: couldn't write frame {}: {}",
+                            frame_num, e);
+                        true
+                    }
+                }
+            }
+        };
+        if done {
+            if let Some(gif) = self.gif.take() {
+                let written = gif.written();
+                log::info!("gif: wrote generations {}..{} ({} frames) to {}",
+                    written.start, written.end, written.len(),
+                    self.options.gif_path);
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn export_gif(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+        if self.gif_requested.take().is_some() {
+            log::warn!("GIF export doesn't work under wasm32");
+        }
+    }
+
+    // Save the complete state of the simulation.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_snapshot(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> io::Result<()> {
+        let snapshot =
+            self.life.snapshot(device, queue, self.rule, self.threshold);
+        snapshot.save(path)?;
+        log::info!("saved generation {} to {}",
+            snapshot.frame_num, path.display());
+        Ok(())
+    }
+
+    // Carry on from a saved state of the simulation.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_snapshot(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        path: &Path,
+    ) -> io::Result<()> {
+        let snapshot = Snapshot::load(path,
+            device.limits().max_texture_dimension_2d)?;
+        self.rule = snapshot.rule;
+        self.threshold = snapshot.threshold;
+
+        if snapshot.dim == self.dim {
+            self.write_params(queue);
+            self.life.restore(device, queue, &snapshot,
+                &self.params, &self.texture);
+            self.sync_compare(device, queue);
+        } else {
+            // The grid takes on the snapshot's size.
+            let params = LifeProg::params(device, snapshot.dim,
+                self.rule, self.threshold);
+            let texture = Texture::new(device, snapshot.dim,
+                wgpu::TextureFormat::R32Float);
+            self.life.restore(device, queue, &snapshot, &params, &texture);
+            self.set_grid(device, queue, snapshot.dim, params, texture);
+        }
+
+        log::info!("loaded generation {} from {}",
+            snapshot.frame_num, path.display());
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn snapshot(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        op: SnapshotOp,
+    ) {
+        let path = PathBuf::from(&self.options.snapshot_path);
+        let result = match op {
+            SnapshotOp::Save => self.save_snapshot(device, queue, &path),
+            SnapshotOp::Load => self.load_snapshot(device, queue, &path),
+        };
+        if let Err(e) = result {
+            log::warn!("snapshot {}: {}", path.display(), e);
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn snapshot(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _op: SnapshotOp,
+    ) {
+        log::warn!("snapshots don't work under wasm32");
+    }
+
+    fn params_data(
+        dim: Dimensions,
+        rule: Rule,
+        threshold: f32,
+    ) -> Params {
+        Params {
+            width: dim.width(),
+            height: dim.height(),
+            threshold,
+            birth: rule.birth(),
+            survive: rule.survive(),
+        }
+    }
+
     fn params(
         device: &wgpu::Device,
         dim: Dimensions,
+        rule: Rule,
+        threshold: f32,
     ) -> Buffer {
         Buffer::new_init(
             device,
             "Life parameters",
             BufferType::Uniform,
-            bytemuck::bytes_of(&Params {
-                width: dim.width(),
-                height: dim.height(),
-                threshold: 0.7,
-            }),
+            bytemuck::bytes_of(&LifeProg::params_data(dim, rule, threshold)),
         )
     }
+
+    // Start a Life grid from random cells, stepped a few times so they
+    // look Life-like.  The window and the terminal both start this way.
+    fn initial_life(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        dim: Dimensions,
+        params: &Buffer,
+        texture: &Texture,
+    ) -> Life {
+        // Get a pseudo-random number generator.
+        // We don't need crypto-strength PRNGs, so we use SmallRng.
+        // Might consider seeding this with something from rand::thread_rng()
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(42);
+        let mut life = Life::new(device, dim, params, texture, &mut rng);
+
+        // Set the initial state for all cells in the life grid.
+        let cell_data: Vec<f32> = Uniform::new_inclusive(0.0, 1.0)
+            .sample_iter(&mut rng).take(dim.area()).collect();
+        life.import(device, queue, &cell_data);
+
+        // Step the algorithm a few times, so the initial image looks Life-like.
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: None
+            });
+        for _ in 0..100 {
+            life.step(&mut command_encoder);
+        }
+        queue.submit(Some(command_encoder.finish()));
+        life
+    }
+
+    // The size of grid that fills the window with cells of the given size.
+    fn fit_grid(
+        window: Dimensions,
+        cell_size: u32,
+    ) -> Dimensions {
+        let cell_size = cell_size.max(1);
+        Dimensions::new(
+            (window.width() / cell_size).max(1),
+            (window.height() / cell_size).max(1),
+        )
+    }
+
+    // Change the size of the grid, keeping whatever cells still fit.
+    fn resize_grid(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        dim: Dimensions,
+    ) {
+        log::info!("main: resizing the grid {:?} -> {:?}", self.dim, dim);
+        let params = LifeProg::params(device, dim, self.rule, self.threshold);
+        let texture = Texture::new(device, dim, wgpu::TextureFormat::R32Float);
+        self.life.resize(device, queue, dim, &params, &texture);
+        self.set_grid(device, queue, dim, params, texture);
+    }
+
+    // Start showing a grid of a different size, which the Life algorithm
+    // has already been given.
+    fn set_grid(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        dim: Dimensions,
+        params: Buffer,
+        texture: Texture,
+    ) {
+        self.trail.resize(device, dim, &params, &texture);
+        self.terrain.rebind(device, dim, &params, &texture);
+        self.renderer.rebind(device, &params, &texture, self.trail.texture(),
+            self.life.ages(), self.clipboard.buf());
+
+        // Edits and selections were made on the old grid.
+        self.history.clear();
+        self.selection = None;
+
+        self.dim = dim;
+        self.params = params;
+        self.texture = texture;
+
+        // Any other runs start again from the new grid.
+        let compared = self.compare.take()
+            .map(|compare| (compare.variants(), compare.show_difference));
+        if let Some((variants, show_difference)) = compared {
+            self.start_compare(device, queue, &variants);
+            if let Some(compare) = &mut self.compare {
+                compare.show_difference = show_difference;
+            }
+        }
+        self.camera.resize(self.view_dim(), dim);
+    }
+
+    // Start running other rules or thresholds alongside the main run,
+    // from where it is now, or stop if there aren't any.
+    fn start_compare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        variants: &[Variant],
+    ) {
+        self.compare = if variants.is_empty() {
+            None
+        } else {
+            let dim = self.dim;
+            let main = compare::Main {
+                life: &self.life,
+                texture: &self.texture,
+                rule: self.rule,
+                threshold: self.threshold,
+                trail: self.trail.texture(),
+                clip: self.clipboard.buf(),
+                colormap: self.colormaps.current(),
+            };
+            Some(Compare::new(device, queue, self.renderer.format(), dim,
+                &|rule, threshold| LifeProg::params(device, dim, rule, threshold),
+                &main, variants))
+        };
+        self.camera.resize(self.view_dim(), self.dim);
+    }
+
+    // Start the other runs again from the main one, after it's been changed
+    // all over.
+    fn sync_compare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if let Some(compare) = &mut self.compare {
+            let mut command_encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("compare")
+                });
+            compare.sync(&mut command_encoder, &self.life);
+            queue.submit(Some(command_encoder.finish()));
+        }
+    }
+
+    // Update the parameters after the rule or threshold has changed,
+    // along with those of any other runs.
+    fn write_params(
+        &mut self,
+        queue: &wgpu::Queue,
+    ) {
+        let dim = self.dim;
+        let write = |params: &Buffer, rule, threshold| {
+            let data = LifeProg::params_data(dim, rule, threshold);
+            queue.write_buffer(params.buf(), 0, bytemuck::bytes_of(&data));
+        };
+        write(&self.params, self.rule, self.threshold);
+        if let Some(compare) = &mut self.compare {
+            compare.set_rule(self.rule, &write);
+            compare.set_threshold(queue, self.threshold, &write);
+        }
+    }
+
+    // Draw the grid the way the window is showing it, into a view of the
+    // given size: in 3D, as the runs being compared, or flat, and then
+    // through any post-processing.  The window, screenshots and recordings
+    // all come from here; the text that goes over the top is only for the
+    // window.  The renderer's camera, overlay and colormap should already
+    // be set.
+    fn render_view(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        dim: Dimensions,
+    ) {
+        let pane_overlay = self.pane_overlay();
+        let show_terrain = self.show_terrain;
+        let terrain = &self.terrain;
+        let compare = &mut self.compare;
+        let renderer = &mut self.renderer;
+        let camera = &self.camera;
+        self.post.render(device, command_encoder, view, dim, |encoder, view| {
+            if show_terrain {
+                // Other runs are only for the flat view.
+                terrain.render(device, queue, encoder, view, dim);
+            } else if let Some(compare) = compare {
+                compare.render(queue, encoder, view, dim, renderer, camera,
+                    &pane_overlay);
+            } else {
+                renderer.render(encoder, view);
+            }
+        });
+    }
 }
 
 impl window::Example for LifeProg {
@@ -70,49 +1239,122 @@ impl window::Example for LifeProg {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> Self {
-        let dim = Dimensions::new(config.width, config.height);
-        let ncells = dim.area();
+        let mut options = Options::from_args();
+        let settings = Config::load(options.config_path.as_deref());
 
-        // Get a pseudo-random number generator.
-        // We don't need crypto-strength PRNGs, so we use SmallRng.
-        // Might consider seeding this with something from rand::thread_rng()
-        let mut rng = rand::rngs::SmallRng::seed_from_u64(42);
+        // The command line wins over the config file.
+        options.present_mode = options.present_mode.or(settings.present_mode);
+        options.frame_rate = options.frame_rate.or(settings.frame_rate);
+        let window = Dimensions::new(config.width, config.height);
+
+        // The grid fills the window, with cells of the chosen size,
+        // unless its size has been given.
+        let dim = options.grid
+            .unwrap_or_else(|| LifeProg::fit_grid(window, options.cell_size));
 
         // Parameters for the game, shared between compute and fragment shaders.
-        let params = LifeProg::params(device, dim);
+        let rule = options.rule;
+        let threshold = options.threshold;
+        let params = LifeProg::params(device, dim, rule, threshold);
 
         // Create a texture that's shared between compute and fragment shaders.
         let texture = Texture::new(&device, dim, wgpu::TextureFormat::R32Float);
 
-        // Initialize the life algorithm.
-        let mut life = Life::new(&device, dim, &params, &texture, &mut rng);
+        // Initialize the life algorithm, with cells that look Life-like.
+        let life = LifeProg::initial_life(device, queue, dim, &params, &texture);
+
+        // Nothing has been copied yet.
+        let clipboard = Clipboard::new(device);
+
+        // Trails of where cells have been, drawn with a colormap of their own.
+        let trail = Trail::new(device, dim, &params, &texture, options.trail_decay);
 
         // Initialize the vertex shader.
-        let renderer = Renderer::new(&config, &device, &params, &texture);
+        let renderer = Renderer::new(config.format, &device, &params, &texture,
+            trail.texture(), life.ages(), clipboard.buf());
+        let trail_colormap = Colormap::named(&options.trail_colormap)
+            .unwrap_or_else(|e| {
+                log::warn!("trail colormap: {}", e);
+                Colormap::built_in().remove(0)
+            });
+        renderer.set_trail_colormap(queue, &trail_colormap);
 
-        // Set the initial state for all cells in the life grid.
-        let cell_data: Vec<f32> = {
-            let u = Uniform::new_inclusive(0.0, 1.0);
-            u.sample_iter(&mut rng).take(ncells).collect()
-        };
+        // The 3D view draws the same cells, as a landscape.
+        let terrain = Terrain::new(config.format, device, window, dim,
+            &params, &texture);
 
-        life.import(&device, &queue, &cell_data);
+        // The renderer's output goes through the effects in the config file.
+        let post = PostChain::new(device, config.format, &settings.post);
 
-        // Step the algorithm a few times, so the initial image looks Life-like.
-        let mut command_encoder =
-            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: None
-            });
-        for _ in 0..100 {
-            life.step(&mut command_encoder);
+        // The colormap can be one of the built-in ones, or come from a file.
+        let mut colormaps = Colormaps::new();
+        if let Some(name) = &options.colormap {
+            if let Err(e) = colormaps.choose(name) {
+                log::warn!("colormap: {}", e);
+            }
         }
-        queue.submit(Some(command_encoder.finish()));
 
-        LifeProg {
+        let mut prog = LifeProg {
             dim,
+            window,
+            rule,
+            threshold,
+            params,
+            texture,
             life,
             renderer,
+            post,
+            compare: None,
+            terrain,
+            show_terrain: options.terrain,
+            colormaps,
+            trail,
+            trails: options.trails,
+            age_colors: false,
+            show_changes: false,
+            bindings: Bindings::with_overrides(&settings.bindings),
+            help: false,
+            text: TextOverlay::new(device, queue, config.format),
+            hud: Hud::new(device, queue, config.format),
+            console: Console::new(device, queue, config.format),
+            command: None,
+            brush: Brush::new(),
+            camera: Camera::new(window, dim),
+            grid_lines: options.grid_lines,
+            panning: false,
+            cursor: (0.0, 0.0),
+            tool: Tool::Draw,
+            select_start: None,
+            selection: None,
+            clipboard,
+            clip_op: None,
+            patterns: Library::load(options.pattern_dir.as_deref(),
+                device.limits().max_texture_dimension_2d),
+            pattern_menu: false,
+            history: History::new(UNDO_MEMORY),
+            history_op: None,
+            snapshot_op: None,
+            screenshot: None,
+            recording_toggled: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            recorder: None,
+            gif_requested: options.gif_range.clone(),
+            goto: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            gif: None,
+            options,
+        };
+        if !prog.options.compare.is_empty() {
+            let variants = prog.options.compare.clone();
+            prog.start_compare(device, queue, &variants);
+        }
+        if prog.options.resume {
+            prog.snapshot(device, queue, SnapshotOp::Load);
+        }
+        if prog.options.record_at_start {
+            prog.toggle_recording(device);
         }
+        prog
     }
 
     /// called on WindowEvent::Resized events
@@ -120,24 +1362,17 @@ impl window::Example for LifeProg {
         &mut self,
         config: &wgpu::SurfaceConfiguration,
         device: &wgpu::Device,
-        queue: &wgpu::Queue,
+        _queue: &wgpu::Queue,
     ) {
-        let dim = Dimensions::new(config.width, config.height);
-        log::info!("main: resizing {:?} -> {:?}", self.dim, dim);
-
-        // Parameters for the game, shared between compute and fragment shaders.
-        let params = LifeProg::params(device, dim);
+        let window = Dimensions::new(config.width, config.height);
+        log::info!("main: resizing {:?} -> {:?}", self.window, window);
 
-        // Create a texture that's shared between compute and fragment shaders.
-        let texture = Texture::new(device, dim, wgpu::TextureFormat::R32Float);
-
-        // Resize the life algorithm.
-        self.life.resize(device, queue, dim, &params, &texture);
-
-        // Rebind the renderer to the new params and texture args.
-        self.renderer.resize(config, device, &params, &texture);
-
-        self.dim = dim;
+        // The grid stays as it is; only the view of it changes.
+        self.renderer.resize(config, device, &self.params, &self.texture,
+            self.trail.texture(), self.life.ages(), self.clipboard.buf());
+        self.terrain.resize(device, window);
+        self.window = window;
+        self.camera.resize(self.view_dim(), self.dim);
     }
 
     /// called to generate each new frame
@@ -148,18 +1383,139 @@ impl window::Example for LifeProg {
         queue: &wgpu::Queue,
         _spawner: &window::Spawner,
     ) {
+        // Do whatever was typed into the console.
+        if let Some(command) = self.command.take() {
+            self.run_command(device, queue, command);
+        }
+
+        // Save or load the simulation state before it moves on.
+        if let Some(op) = self.snapshot_op.take() {
+            self.snapshot(device, queue, op);
+        }
+
+        // Likewise for copying and pasting, and undoing.
+        if let Some(op) = self.clip_op.take() {
+            self.clipboard_op(device, queue, op);
+        }
+        if let Some(op) = self.history_op.take() {
+            self.undo_redo(device, queue, op);
+        }
+
+        // Apply any painting that's been done with the mouse.
+        self.paint(device, queue);
+
         let mut command_encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
                 label: None
             });
 
-        // Run the life algorithm one step.
-        self.life.step(&mut command_encoder);
+        // Run the life algorithm one step, along with any other runs,
+        // or a batch of steps on the way to a generation.
+        let end = match self.goto {
+            Some(generation) => generation.min(self.life.frame_num() + GOTO_BATCH),
+            None => self.life.frame_num() + 1,
+        };
+        while self.life.frame_num() < end {
+            self.life.step(&mut command_encoder);
+            if let Some(compare) = &mut self.compare {
+                compare.step(&mut command_encoder);
+            }
+        }
+        self.hud.enqueue(&mut command_encoder, &self.life);
+        if let Some(generation) = self.goto {
+            if self.life.frame_num() < generation {
+                self.console.reprint(format!("going to generation {}: at {}",
+                    generation, self.life.frame_num()));
+            } else {
+                self.goto = None;
+                self.trail.reset();
+                self.console.reprint(format!("at generation {}", generation));
+            }
+        }
+
+        // Fade the trail, and lay down the cells that are alive now.
+        if self.trails {
+            self.trail.step(queue, &mut command_encoder);
+        }
 
         // Render the life cells into actual pixels, and display them.
-        self.renderer.render(&mut command_encoder, &view);
+        self.renderer.set_camera(queue, &self.camera);
+        self.renderer.set_overlay(queue, &self.overlay());
+        if self.colormaps.take_changed() {
+            let colormap = self.colormaps.current();
+            self.renderer.set_colormap(queue, colormap);
+            self.terrain.set_colormap(queue, colormap);
+            if let Some(compare) = &self.compare {
+                compare.set_colormap(queue, colormap);
+            }
+        }
+        self.render_view(device, queue, &mut command_encoder, view, self.window);
+
+        // Draw the help or the pattern menu over the top, in the middle
+        // of the window.
+        self.text.update(device, queue);
+        let scale = (self.window.height() / TEXT_SCALE_HEIGHT).max(1);
+        let text = self.text.dim();
+        let origin = (
+            self.window.width().saturating_sub(text.width() * scale) / 2,
+            self.window.height().saturating_sub(text.height() * scale) / 2,
+        );
+        self.text.render(queue, &mut command_encoder, &view, self.window,
+            origin, scale);
+
+        // And the HUD in the corner.
+        let info = HudInfo {
+            grid: self.life.dimensions(),
+            threshold: self.threshold,
+            rule: self.rule,
+        };
+        self.hud.frame(self.life.frame_num());
+        self.hud.render(device, queue, &mut command_encoder, &view, self.window,
+            scale, self.life.frame_num(), &info);
+
+        // The console drops down over everything.
+        self.console.render(device, queue, &mut command_encoder, &view,
+            self.window, scale);
+
+        if std::mem::take(&mut self.recording_toggled) {
+            self.toggle_recording(device);
+        }
 
         queue.submit(Some(command_encoder.finish()));
+        self.hud.submitted();
+
+        // Keep a copy of the frame if we're recording.  It's rendered
+        // again at the recording's size, which can be different from the
+        // window's, so it goes in after the frame for the window has been
+        // submitted, with the uniforms that were written for it.
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(mut recorder) = self.recorder.take() {
+            let mut command_encoder =
+                device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("recorder")
+                });
+            let frame_num = self.life.frame_num();
+            recorder.enqueue(device, &mut command_encoder, frame_num,
+                |encoder, view, dim| self.render_view(device, queue, encoder, view, dim));
+            queue.submit(Some(command_encoder.finish()));
+            recorder.submitted();
+            recorder.write_ready(device);
+            self.recorder = Some(recorder);
+        }
+
+        // Keep a copy of the generation if we're exporting a GIF.
+        self.export_gif(device, queue);
+
+        // Save a copy of what was just rendered, if that was asked for.
+        if let Some(scale) = self.screenshot.take() {
+            let path = PathBuf::from(
+                format!("zounds-{:06}.png", self.life.frame_num()));
+            if let Err(e) = Screenshot::save(device, queue, self.renderer.format(),
+                self.window, scale, &path,
+                |encoder, view, dim| self.render_view(device, queue, encoder, view, dim)) {
+                log::warn!("couldn't save {}: {}", path.display(), e);
+            }
+        }
 
         // XXX: create and destroy a texture, to confirm that this is still going
         // even when console output stops (this shows up in RUST_LOG=debug output)
@@ -174,45 +1530,191 @@ impl window::Example for LifeProg {
     fn key_press(
         &mut self,
         keycode: VirtualKeyCode,
+        modifiers: ModifiersState,
     ) -> Option<WindowOps> {
-        match keycode {
-            VirtualKeyCode::Escape => Some(WindowOps::Quit),
-            VirtualKeyCode::F => Some(WindowOps::FullScreen),
-            VirtualKeyCode::W => Some(WindowOps::UnFullScreen),
-            _ => None,
+        if self.console.is_open() {
+            self.command = self.console.key(keycode);
+            return None;
+        }
+        if self.pattern_menu {
+            self.pattern_menu_key(keycode);
+            return None;
+        }
+
+        self.bindings.lookup(keycode, modifiers)
+            .and_then(|action| self.perform(action))
+    }
+
+    /// called for any other window events
+    fn update(
+        &mut self,
+        event: WindowEvent,
+    ) {
+        // Typing goes to the console, if it's open.
+        if let WindowEvent::ReceivedCharacter(c) = event {
+            self.console.type_char(c);
         }
     }
 
     /// called when a mouse button is pressed or released
     fn mouse_press(
         &mut self,
-        _state: winit::event::ElementState,
-        _button: winit::event::MouseButton,
+        state: ElementState,
+        button: MouseButton,
+        _modifiers: ModifiersState,
     ) {
-        // println!("mouse_press: state {:?}, button {:?}", _state, _button);
-        // empty
+        // The middle button drags the view around, whatever the tool.
+        // In the 3D view, either button turns the camera, and there's
+        // nothing to draw on.
+        if button == MouseButton::Middle
+            || (self.show_terrain && button == MouseButton::Left) {
+            self.panning = state == ElementState::Pressed;
+            return;
+        }
+        if self.show_terrain {
+            return;
+        }
+
+        // Presses in the bars around the grid don't do anything, but
+        // releases there still finish what was started.
+        let pressed = state == ElementState::Pressed;
+        if pressed && !self.cursor_on_grid() {
+            return;
+        }
+        match self.tool {
+            Tool::Draw => {},
+
+            // The left button drags out a selection, and the right button
+            // gets rid of it.
+            Tool::Select => {
+                let pos = self.cursor_cell();
+                match button {
+                    MouseButton::Left if pressed => {
+                        self.select_start = Some(pos);
+                        self.selection = Some(selection_rect(pos, pos));
+                    },
+                    MouseButton::Left => self.select_start = None,
+                    MouseButton::Right if pressed => self.selection = None,
+                    _ => {},
+                }
+                return;
+            },
+
+            // The left button pastes, and the right button stops pasting.
+            Tool::Paste => {
+                match button {
+                    MouseButton::Left if pressed => {
+                        self.clip_op = Some(ClipOp::Paste(self.paste_origin()));
+                    },
+                    MouseButton::Right if pressed => self.tool = Tool::Draw,
+                    _ => {},
+                }
+                return;
+            },
+        }
+
+        // The left button draws live cells, and the right button erases them.
+        let value = match button {
+            MouseButton::Left => 1.0,
+            MouseButton::Right => 0.0,
+            _ => return,
+        };
+        match state {
+            ElementState::Pressed => {
+                let pos = self.cursor_cell();
+                self.brush.start(value, pos);
+            },
+            ElementState::Released => self.brush.stop(),
+        }
     }
 
     /// called when the mouse is moved
     fn mouse_move(
         &mut self,
-        _x: f64,
-        _y: f64,
+        x: f64,
+        y: f64,
     ) {
-        // println!("mouse_move: x {:?}, y {:?}", _x, _y);
-        // empty
+        if self.panning {
+            let (ox, oy) = self.cursor;
+            if self.show_terrain {
+                self.terrain.rotate((x - ox, y - oy));
+            } else {
+                self.camera.pan((x - ox, y - oy));
+            }
+        }
+        self.cursor = (x, y);
+        let pos = self.cursor_cell();
+        if self.cursor_on_grid() {
+            self.brush.move_to(pos);
+        } else {
+            self.brush.lift();
+        }
+        if let Some(start) = self.select_start {
+            self.selection = Some(selection_rect(start, pos));
+        }
     }
 
-    /// called for any WindowEvent not handled by the framework
-    fn update(
+    /// called when the scroll wheel moves
+    fn mouse_scroll(
+        &mut self,
+        delta: MouseScrollDelta,
+        _modifiers: ModifiersState,
+    ) {
+        // The scroll wheel zooms in and out around the mouse.
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y,
+            MouseScrollDelta::PixelDelta(pos) => pos.y as f32 / 100.0,
+        };
+        if self.show_terrain {
+            self.terrain.zoom(ZOOM_STEP.powf(lines));
+            return;
+        }
+        let pos = self.pane_cursor();
+        self.camera.zoom_at(ZOOM_STEP.powf(lines), pos);
+    }
+
+    /// called just before the program exits
+    fn quit(
         &mut self,
-        _event: winit::event::WindowEvent
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
     ) {
-        // empty
+        // Don't lose the frames that are still on their way back from the GPU.
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(recorder) = &mut self.recorder {
+            recorder.stop(_device);
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.finish_run(_device, _queue);
+    }
+
+    /// called once the window is set up, to see how to show frames
+    fn display(&self) -> window::Display {
+        window::Display {
+            present_mode: self.options.present_mode,
+            frame_rate: self.options.frame_rate.unwrap_or_default(),
+        }
     }
 }
 
 /// run example
 fn main() {
+    // Without a window, the whole run happens offscreen, or in the terminal.
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        let options = Options::from_args();
+        if options.terminal {
+            if let Err(e) = terminal::run(&options) {
+                eprintln!("zounds: terminal: {}", e);
+                std::process::exit(1);
+            }
+            return;
+        }
+        if let Some(frames) = options.headless {
+            headless::run::<LifeProg>(options.size, frames);
+            return;
+        }
+    }
     window::run::<LifeProg>();
 }
@@ -0,0 +1,305 @@
+// Command-line options.
+
+use std::ops::Range;
+use std::process;
+
+use crate::{
+    compare::{MAX_RUNS, Variant},
+    dimensions::Dimensions,
+    rule::Rule,
+    window::{FrameRate, PresentMode},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordFormat {
+    Png,    // a numbered sequence of PNG files
+    Y4m,    // a raw YUV4MPEG2 stream
+}
+
+pub struct Options {
+    pub grid: Option<Dimensions>,       // None means to fill the window
+    pub cell_size: u32,                 // in pixels, when filling the window
+    pub grid_lines: bool,
+    pub rule: Rule,
+    pub threshold: f32,                 // cells above this are alive
+    pub snapshot_path: String,
+    pub resume: bool,
+    pub record_path: String,            // "-" means stdout
+    pub record_format: RecordFormat,
+    pub record_fps: u32,
+    pub record_at_start: bool,
+    pub gif_path: String,
+    pub gif_range: Option<Range<usize>>,
+    pub gif_delay: u16,                 // in hundredths of a second
+    pub gif_scale: u32,
+    pub pattern_dir: Option<String>,    // where to find more RLE patterns
+    pub config_path: Option<String>,
+    pub colormap: Option<String>,       // a built-in name, or a palette file
+    pub trails: bool,
+    pub trail_decay: f32,               // how much of a trail is left each step
+    pub trail_colormap: String,
+    pub compare: Vec<Variant>,          // rules or thresholds to run alongside
+    pub terrain: bool,                  // start in the 3D view
+    pub present_mode: Option<PresentMode>,
+    pub frame_rate: Option<FrameRate>,
+    pub headless: Option<usize>,        // frames to run for, with no window
+    pub size: Dimensions,               // of the frames, with no window
+    pub exit_screenshot: Option<String>,
+    pub exit_pattern: Option<String>,
+    pub terminal: bool,                 // draw in the terminal, not a window
+    pub braille: bool,                  // with braille, not half blocks
+}
+
+// $XDG_CONFIG_HOME/zounds, or ~/.config/zounds, with a name added.
+fn config_dir(
+    name: &str,
+) -> Option<String> {
+    let config = std::env::var("XDG_CONFIG_HOME").ok()
+        .or_else(|| std::env::var("HOME").ok().map(|h| format!("{}/.config", h)))?;
+    Some(format!("{}/zounds/{}", config, name))
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            grid: None,
+            cell_size: 1,
+            grid_lines: false,
+            rule: Rule::default(),
+            threshold: 0.7,
+            snapshot_path: "zounds.snap".to_string(),
+            resume: false,
+            record_path: "zounds".to_string(),
+            record_format: RecordFormat::Png,
+            record_fps: 60,
+            record_at_start: false,
+            gif_path: "zounds.gif".to_string(),
+            gif_range: None,
+            gif_delay: 4,
+            gif_scale: 1,
+            pattern_dir: config_dir("patterns"),
+            config_path: config_dir("config.toml"),
+            colormap: None,
+            trails: false,
+            trail_decay: 0.95,
+            trail_colormap: "magma".to_string(),
+            compare: Vec::new(),
+            terrain: false,
+            present_mode: None,
+            frame_rate: None,
+            headless: None,
+            size: Dimensions::new(1280, 720),
+            exit_screenshot: None,
+            exit_pattern: None,
+            terminal: false,
+            braille: false,
+        }
+    }
+}
+
+fn usage(msg: &str) -> ! {
+    eprintln!("zounds: {}", msg);
+    eprintln!("usage: zounds [options]");
+    eprintln!("  --grid WxH             the size of the grid (default: fill the window)");
+    eprintln!("  --cell-size N          fill the window with cells N pixels wide");
+    eprintln!("  --grid-lines           draw lines between cells when zoomed in");
+    eprintln!("  --rule RULE            the rule to run, e.g. B3/S23");
+    eprintln!("  --threshold X          cells with values above X are alive");
+    eprintln!("  --snapshot PATH        where snapshots are saved and loaded");
+    eprintln!("  --resume               start from the saved snapshot");
+    eprintln!("  --record PATH          record frames to PATH (\"-\" for stdout)");
+    eprintln!("  --record-format FMT    record as \"png\" (numbered files) or \"y4m\"");
+    eprintln!("  --record-fps N         frame rate written into y4m streams");
+    eprintln!("  --record-start         start recording immediately");
+    eprintln!("  --gif N..M             export generations N..M as an animated GIF");
+    eprintln!("  --gif-path PATH        where to write the GIF");
+    eprintln!("  --gif-delay N          GIF frame delay, in hundredths of a second");
+    eprintln!("  --gif-scale N          GIF pixels per cell");
+    eprintln!("  --patterns DIR         load more patterns from the RLE files in DIR");
+    eprintln!("  --config PATH          read settings and key bindings from PATH");
+    eprintln!("  --colormap NAME        classic, viridis, magma, cividis, greyscale,");
+    eprintln!("                         cyclic, or a palette file (.png or hex colors)");
+    eprintln!("  --trails               show trails of where cells have been");
+    eprintln!("  --trail-decay X        how much of a trail is left after each step");
+    eprintln!("  --trail-colormap NAME  the colormap for trails (default: magma)");
+    eprintln!("  --compare A,B,C        run up to 3 other rules or thresholds alongside");
+    eprintln!("  --terrain              start with the 3D view of the grid");
+    eprintln!("  --present-mode MODE    fifo, mailbox (the default) or immediate");
+    eprintln!("  --fps N                draw at most N frames a second, or \"monitor\"");
+    eprintln!("                         (the default) or \"uncapped\"");
+    eprintln!("  --headless N           render N frames offscreen, with no window");
+    eprintln!("  --size WxH             the size of the frames with no window");
+    eprintln!("  --screenshot PATH      save a screenshot to PATH on exit");
+    eprintln!("  --export PATH          save the grid to PATH as an RLE pattern on exit");
+    eprintln!("  --terminal             draw in the terminal, with no window");
+    eprintln!("  --braille              draw with braille dots in the terminal");
+    process::exit(2);
+}
+
+fn value(
+    args: &mut impl Iterator<Item = String>,
+    name: &str,
+) -> String {
+    args.next().unwrap_or_else(|| usage(&format!("{} needs a value", name)))
+}
+
+fn number<T: std::str::FromStr>(
+    args: &mut impl Iterator<Item = String>,
+    name: &str,
+) -> T {
+    value(args, name).parse()
+        .unwrap_or_else(|_| usage(&format!("{} needs a number", name)))
+}
+
+// Parse a range of generations, in the form "N..M".
+fn range(
+    args: &mut impl Iterator<Item = String>,
+    name: &str,
+) -> Range<usize> {
+    let v = value(args, name);
+    let bad = format!("{} needs a range like 100..200", name);
+    let (start, end) = v.split_once("..").unwrap_or_else(|| usage(&bad));
+    let start: usize = start.parse().unwrap_or_else(|_| usage(&bad));
+    let end: usize = end.parse().unwrap_or_else(|_| usage(&bad));
+    if start >= end {
+        usage(&bad);
+    }
+    start..end
+}
+
+impl Options {
+    // Parse the command line.  This exits on malformed arguments.
+    pub fn from_args() -> Self {
+        Options::parse(std::env::args().skip(1))
+    }
+
+    fn parse(args: impl Iterator<Item = String>) -> Self {
+        let mut opts = Options::default();
+        let mut args = args;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--grid" => {
+                    opts.grid = Some(value(&mut args, &arg).parse()
+                        .unwrap_or_else(|e: String| usage(&e)));
+                },
+                "--cell-size" => {
+                    opts.cell_size = number(&mut args, &arg);
+                    if opts.cell_size == 0 {
+                        usage("--cell-size needs to be at least 1");
+                    }
+                },
+                "--grid-lines" => {
+                    opts.grid_lines = true;
+                },
+                "--rule" => {
+                    opts.rule = value(&mut args, &arg).parse()
+                        .unwrap_or_else(|e: String| usage(&e));
+                },
+                "--threshold" => {
+                    opts.threshold = number(&mut args, &arg);
+                },
+                "--snapshot" => {
+                    opts.snapshot_path = value(&mut args, &arg);
+                },
+                "--resume" => {
+                    opts.resume = true;
+                },
+                "--record" => {
+                    opts.record_path = value(&mut args, &arg);
+                    if opts.record_path == "-" {
+                        opts.record_format = RecordFormat::Y4m;
+                    }
+                },
+                "--record-format" => {
+                    opts.record_format = match value(&mut args, &arg).as_str() {
+                        "png" => RecordFormat::Png,
+                        "y4m" => RecordFormat::Y4m,
+                        f => usage(&format!("unknown record format \"{}\"", f)),
+                    };
+                },
+                "--record-fps" => {
+                    opts.record_fps = number(&mut args, &arg);
+                },
+                "--record-start" => {
+                    opts.record_at_start = true;
+                },
+                "--gif" => {
+                    opts.gif_range = Some(range(&mut args, &arg));
+                },
+                "--gif-path" => {
+                    opts.gif_path = value(&mut args, &arg);
+                },
+                "--gif-delay" => {
+                    opts.gif_delay = number(&mut args, &arg);
+                },
+                "--gif-scale" => {
+                    opts.gif_scale = number(&mut args, &arg);
+                },
+                "--patterns" => {
+                    opts.pattern_dir = Some(value(&mut args, &arg));
+                },
+                "--config" => {
+                    opts.config_path = Some(value(&mut args, &arg));
+                },
+                "--colormap" => {
+                    opts.colormap = Some(value(&mut args, &arg));
+                },
+                "--trails" => {
+                    opts.trails = true;
+                },
+                "--trail-decay" => {
+                    opts.trail_decay = number(&mut args, &arg);
+                    if !(0.0..1.0).contains(&opts.trail_decay) {
+                        usage("--trail-decay needs to be from 0 up to 1");
+                    }
+                },
+                "--trail-colormap" => {
+                    opts.trail_colormap = value(&mut args, &arg);
+                },
+                "--compare" => {
+                    opts.compare = value(&mut args, &arg).split(',')
+                        .map(str::parse)
+                        .collect::<Result<_, _>>()
+                        .unwrap_or_else(|e: String| usage(&e));
+                    if opts.compare.len() >= MAX_RUNS {
+                        usage(&format!("--compare takes at most {}", MAX_RUNS - 1));
+                    }
+                },
+                "--terrain" => {
+                    opts.terrain = true;
+                },
+                "--present-mode" => {
+                    opts.present_mode = Some(value(&mut args, &arg).parse()
+                        .unwrap_or_else(|e: String| usage(&e)));
+                },
+                "--fps" => {
+                    opts.frame_rate = Some(value(&mut args, &arg).parse()
+                        .unwrap_or_else(|e: String| usage(&e)));
+                },
+                "--headless" => {
+                    opts.headless = Some(number(&mut args, &arg));
+                },
+                "--size" => {
+                    opts.size = value(&mut args, &arg).parse()
+                        .unwrap_or_else(|e: String| usage(&e));
+                },
+                "--screenshot" => {
+                    opts.exit_screenshot = Some(value(&mut args, &arg));
+                },
+                "--export" => {
+                    opts.exit_pattern = Some(value(&mut args, &arg));
+                },
+                "--terminal" => {
+                    opts.terminal = true;
+                },
+                "--braille" => {
+                    opts.braille = true;
+                },
+                _ => usage(&format!("unknown option \"{}\"", arg)),
+            }
+        }
+
+        opts
+    }
+}
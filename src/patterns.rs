@@ -0,0 +1,155 @@
+// A catalog of well-known patterns that can be stamped into the grid.
+//
+// The built-in patterns are compiled in.  More can be added by putting RLE
+// files (with names ending in ".rle") into the pattern directory.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path};
+
+use crate::rle::Pattern;
+
+// How many patterns the menu shows at once.
+const MENU_ROWS: usize = 20;
+
+// Name and RLE for each built-in pattern.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("Glider",
+        "x = 3, y = 3\nbob$2bo$3o!"),
+    ("Lightweight spaceship",
+        "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!"),
+    ("Gosper glider gun",
+        "x = 36, y = 9\n\
+         24bo11b$22bobo11b$12b2o6b2o12b2o$11bo3bo4b2o12b2o$\
+         2o8bo5bo3b2o14b$2o8bo3bob2o4bobo11b$10bo5bo7bo11b$\
+         11bo3bo20b$12b2o22b!"),
+    ("Pulsar",
+        "x = 13, y = 13\n\
+         2b3o3b3o2b2$o4bobo4bo$o4bobo4bo$o4bobo4bo$2b3o3b3o2b2$\
+         2b3o3b3o2b$o4bobo4bo$o4bobo4bo$o4bobo4bo2$2b3o3b3o!"),
+    ("R-pentomino",
+        "x = 3, y = 3\nb2o$2ob$bo!"),
+    ("Acorn",
+        "x = 7, y = 3\nbo5b$3bo3b$2o2b3o!"),
+    // These grow forever, by turning into switch engines that leave
+    // a trail of blocks behind them.  Switch engines are what the smaller
+    // breeders are built from; the breeders themselves run to thousands
+    // of cells, and can be added as RLE files.
+    ("Switch engine (10 cells)",
+        "x = 8, y = 6\n6bob$4bob2o$4bobob$4bo3b$2bo5b$obo!"),
+    ("Switch engine (5x5)",
+        "x = 5, y = 5\n3obo$o4b$3b2o$b2obo$obobo!"),
+];
+
+pub struct Library {
+    patterns: Vec<Pattern>,
+    selected: usize,
+    max_side: u32,          // of patterns that can be loaded
+}
+
+impl Library {
+    // The built-in patterns, followed by any from the given directory.
+    // Patterns bigger than max_side cells across or down aren't loaded.
+    pub fn load(
+        dir: Option<&str>,
+        max_side: u32,
+    ) -> Self {
+        let patterns: Vec<Pattern> = BUILT_IN.iter().map(|(name, rle)| {
+            Pattern::parse(rle, name, max_side).expect("bad built-in pattern")
+        }).collect();
+
+        let mut library = Library {
+            patterns,
+            selected: 0,
+            max_side,
+        };
+        if let Some(dir) = dir {
+            library.load_dir(dir);
+        }
+        library
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn load_dir(
+        &mut self,
+        dir: &str,
+    ) {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            // It's fine for there not to be one.
+            Err(_) => return,
+        };
+
+        let mut paths: Vec<_> = entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().map_or(false, |ext| ext == "rle"))
+            .collect();
+        paths.sort();
+
+        for path in paths {
+            match self.load_file(&path) {
+                Ok(pattern) => {
+                    log::info!("patterns: loaded {} from {}",
+                        pattern.name, path.display());
+                    self.patterns.push(pattern);
+                },
+                Err(e) => log::warn!("patterns: {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn load_dir(
+        &mut self,
+        _dir: &str,
+    ) {
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_file(
+        &self,
+        path: &Path,
+    ) -> Result<Pattern, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let stem = path.file_stem().map_or_else(String::new,
+            |s| s.to_string_lossy().into_owned());
+        Pattern::parse(&text, &stem, self.max_side)
+    }
+
+    // Add a pattern to the end of the menu, and select it.
+    pub fn add(
+        &mut self,
+        pattern: Pattern,
+    ) {
+        self.patterns.push(pattern);
+        self.selected = self.patterns.len() - 1;
+    }
+
+    pub fn current(&self) -> &Pattern {
+        &self.patterns[self.selected]
+    }
+
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.patterns.len();
+    }
+
+    pub fn prev(&mut self) {
+        self.selected = (self.selected + self.patterns.len() - 1) % self.patterns.len();
+    }
+
+    // The lines of the menu, marking the selected pattern, and scrolled
+    // to keep it in view.
+    pub fn menu(&self) -> Vec<String> {
+        let first = self.selected.saturating_sub(MENU_ROWS / 2)
+            .min(self.patterns.len().saturating_sub(MENU_ROWS));
+        let mut lines = vec![
+            "Patterns: up/down to choose, enter to stamp, escape to close".to_string(),
+            String::new(),
+        ];
+        lines.extend(self.patterns.iter().enumerate().skip(first).take(MENU_ROWS)
+            .map(|(i, pattern)| format!("{} {:2}: {} ({}x{})",
+                if i == self.selected { ">" } else { " " },
+                i + 1, pattern.name,
+                pattern.dim.width(), pattern.dim.height())));
+        lines
+    }
+}
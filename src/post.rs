@@ -0,0 +1,383 @@
+// Post-processing: fullscreen passes over the rendered cells.
+//
+// The chain of effects comes from the config file, and they're applied in
+// the order they're given, like
+//
+//     [[post]]
+//     effect = "bloom"
+//     strength = 1.5
+//
+//     [[post]]
+//     effect = "vignette"
+//
+// Each effect is a WGSL fragment shader with its own parameters, and has
+// one or more stages.  A stage reads what the stage before it drew, and
+// also what went into the effect, so that bloom can add its glow back onto
+// the picture.  Whatever view of the grid is showing (flat, 3D, or the
+// runs being compared) draws into one of three intermediate textures, the
+// stages pass the picture between them, and the last stage draws into the
+// real target.
+
+use serde::Deserialize;
+use std::borrow::Cow;
+
+use crate::{
+    bindable::{Bindable, BindAccess, Buffer, BufferType, Sampler},
+    dimensions::Dimensions,
+};
+
+fn one() -> f32 { 1.0 }
+fn half() -> f32 { 0.5 }
+fn bloom_threshold() -> f32 { 0.6 }
+fn blur_radius() -> f32 { 8.0 }
+fn scanline_spacing() -> f32 { 3.0 }
+fn vignette_radius() -> f32 { 0.6 }
+
+// A misspelled parameter would otherwise quietly get its default.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "effect", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Effect {
+    Bloom {
+        #[serde(default = "bloom_threshold")]
+        threshold: f32,     // how bright a pixel has to be to glow
+        #[serde(default = "one")]
+        strength: f32,
+        #[serde(default = "blur_radius")]
+        radius: f32,        // in pixels
+    },
+    Blur {
+        #[serde(default = "blur_radius")]
+        radius: f32,        // in pixels
+    },
+    Scanlines {
+        #[serde(default = "half")]
+        strength: f32,      // how dark the lines get, from 0 to 1
+        #[serde(default = "scanline_spacing")]
+        spacing: f32,       // in pixels
+    },
+    Vignette {
+        #[serde(default = "half")]
+        strength: f32,      // how dark the corners get, from 0 to 1
+        #[serde(default = "vignette_radius")]
+        radius: f32,        // where it starts, as a fraction of the way out
+    },
+    Edges {
+        #[serde(default = "one")]
+        strength: f32,      // 1 shows only the edges
+    },
+}
+
+impl Effect {
+    fn name(&self) -> &'static str {
+        match self {
+            Effect::Bloom { .. } => "bloom",
+            Effect::Blur { .. } => "blur",
+            Effect::Scanlines { .. } => "scanlines",
+            Effect::Vignette { .. } => "vignette",
+            Effect::Edges { .. } => "edges",
+        }
+    }
+
+    // The effect's shader, which goes after post.wgsl.
+    fn source(&self) -> &'static str {
+        match self {
+            Effect::Bloom { .. } => include_str!("post_bloom.wgsl"),
+            Effect::Blur { .. } => include_str!("post_blur.wgsl"),
+            Effect::Scanlines { .. } => include_str!("post_scanlines.wgsl"),
+            Effect::Vignette { .. } => include_str!("post_vignette.wgsl"),
+            Effect::Edges { .. } => include_str!("post_edges.wgsl"),
+        }
+    }
+
+    // The entry points of the stages, in the order they run.
+    fn stages(&self) -> &'static [&'static str] {
+        match self {
+            Effect::Bloom { .. } => &["fs_bright", "fs_horizontal", "fs_vertical_add"],
+            Effect::Blur { .. } => &["fs_horizontal", "fs_vertical"],
+            Effect::Scanlines { .. }
+            | Effect::Vignette { .. }
+            | Effect::Edges { .. } => &["fs_main"],
+        }
+    }
+
+    // The parameters, as the struct at binding 0 in the effect's shader.
+    fn uniform(&self) -> [f32; 4] {
+        match *self {
+            Effect::Bloom { threshold, strength, radius } =>
+                [threshold, strength, radius, 0.0],
+            Effect::Blur { radius } => [radius, 0.0, 0.0, 0.0],
+            Effect::Scanlines { strength, spacing } => [strength, spacing, 0.0, 0.0],
+            Effect::Vignette { strength, radius } => [strength, radius, 0.0, 0.0],
+            Effect::Edges { strength } => [strength, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// A texture that can be drawn into, and then read by the next stage.
+struct RenderTarget {
+    view: wgpu::TextureView,
+}
+
+impl RenderTarget {
+    fn new(
+        device: &wgpu::Device,
+        dim: Dimensions,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("post-processing"),
+            size: wgpu::Extent3d {
+                width: dim.width(),
+                height: dim.height(),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                 | wgpu::TextureUsages::TEXTURE_BINDING,
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        RenderTarget {
+            view,
+        }
+    }
+}
+
+impl Bindable for RenderTarget {
+    fn binding_resource(
+        &self
+    ) -> wgpu::BindingResource {
+        wgpu::BindingResource::TextureView(&self.view)
+    }
+
+    fn binding_type(
+        &self,
+        _: BindAccess,
+    ) -> wgpu::BindingType {
+        wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+struct Stage {
+    pipeline: wgpu::RenderPipeline,
+    effect: usize,
+}
+
+// A stage, hooked up to the textures it reads, and where it draws:
+// one of the targets, or None for the real target.
+struct Pass {
+    stage: usize,
+    bind_group: wgpu::BindGroup,
+    output: Option<usize>,
+}
+
+pub struct PostChain {
+    stages: Vec<Stage>,
+    params: Vec<Buffer>,    // one for each effect
+    sampler: Sampler,
+    layout: wgpu::BindGroupLayout,
+    format: wgpu::TextureFormat,
+    targets: Vec<RenderTarget>,
+    passes: Vec<Pass>,
+    dim: Option<Dimensions>,    // of the targets, once they've been made
+}
+
+impl PostChain {
+    pub fn new(
+        device: &wgpu::Device,
+        format: wgpu::TextureFormat,
+        effects: &[Effect],
+    ) -> Self {
+        let params: Vec<Buffer> = effects.iter().map(|effect| {
+            Buffer::new_init(device, effect.name(), BufferType::Uniform,
+                bytemuck::bytes_of(&effect.uniform()))
+        }).collect();
+        let sampler = Sampler::new(device,
+            wgpu::AddressMode::ClampToEdge,
+            wgpu::FilterMode::Linear,
+        );
+
+        // The effect's parameters, the sampler, the source and the input.
+        let texture = wgpu::BindingType::Texture {
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            view_dimension: wgpu::TextureViewDimension::D2,
+            multisampled: false,
+        };
+        let types = [
+            wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: wgpu::BufferSize::new(
+                    std::mem::size_of::<[f32; 4]>() as _),
+            },
+            sampler.binding_type(BindAccess::ReadSampled),
+            texture,
+            texture,
+        ];
+        let layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &types.iter().enumerate().map(|(idx, &ty)| {
+                    wgpu::BindGroupLayoutEntry {
+                        binding: idx as _,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty,
+                        count: None,
+                    }}).collect::<Vec<_>>(),
+                label: Some("post-processing"),
+            });
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("post-processing"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+
+        let mut stages = Vec::new();
+        for (i, effect) in effects.iter().enumerate() {
+            let shader = device.create_shader_module(
+                &wgpu::ShaderModuleDescriptor {
+                    label: Some(effect.name()),
+                    source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!("{}{}",
+                        include_str!("post.wgsl"), effect.source()))),
+                });
+            for &entry_point in effect.stages() {
+                let pipeline = device.create_render_pipeline(
+                    &wgpu::RenderPipelineDescriptor {
+                        label: Some(effect.name()),
+                        layout: Some(&pipeline_layout),
+                        vertex: wgpu::VertexState {
+                            module: &shader,
+                            entry_point: "vs_main",
+                            buffers: &[],
+                        },
+                        fragment: Some(wgpu::FragmentState {
+                            module: &shader,
+                            entry_point,
+                            targets: &[format.into()],
+                        }),
+                        primitive: wgpu::PrimitiveState::default(),
+                        depth_stencil: None,
+                        multisample: wgpu::MultisampleState::default(),
+                    });
+                stages.push(Stage {
+                    pipeline,
+                    effect: i,
+                });
+            }
+        }
+        if !effects.is_empty() {
+            log::info!("post: {}", effects.iter()
+                .map(Effect::name).collect::<Vec<_>>().join(", "));
+        }
+
+        PostChain {
+            stages,
+            params,
+            sampler,
+            layout,
+            format,
+            targets: Vec::new(),
+            passes: Vec::new(),
+            dim: None,
+        }
+    }
+
+    // Make the intermediate textures, and work out which stage reads and
+    // draws into which.
+    fn allocate(
+        &mut self,
+        device: &wgpu::Device,
+        dim: Dimensions,
+    ) {
+        self.targets = (0..3)
+            .map(|_| RenderTarget::new(device, dim, self.format))
+            .collect();
+
+        // The scene is drawn into target 0.
+        let mut passes = Vec::new();
+        let mut source = 0;
+        let mut input = 0;
+        for (i, stage) in self.stages.iter().enumerate() {
+            if i == 0 || self.stages[i - 1].effect != stage.effect {
+                input = source;
+            }
+            let output = if i + 1 == self.stages.len() {
+                None
+            } else {
+                (0..3).find(|&t| t != source && t != input)
+            };
+            let args: [&dyn Bindable; 4] = [&self.params[stage.effect],
+                &self.sampler, &self.targets[source], &self.targets[input]];
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &self.layout,
+                entries: &args.iter().enumerate().map(|(idx, arg)| {
+                    wgpu::BindGroupEntry {
+                        binding: idx as _,
+                        resource: arg.binding_resource(),
+                    }}).collect::<Vec<_>>(),
+                label: None,
+            });
+            passes.push(Pass {
+                stage: i,
+                bind_group,
+                output,
+            });
+            source = output.unwrap_or(0);
+        }
+        self.passes = passes;
+        self.dim = Some(dim);
+    }
+
+    // Have the scene function draw the grid, and run the effects over it,
+    // ending up in the view, which is the given size.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        dim: Dimensions,
+        scene: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView),
+    ) {
+        if self.stages.is_empty() {
+            scene(encoder, view);
+            return;
+        }
+        if self.dim != Some(dim) {
+            self.allocate(device, dim);
+        }
+
+        scene(encoder, &self.targets[0].view);
+        for pass in &self.passes {
+            let target = match pass.output {
+                Some(t) => &self.targets[t].view,
+                None => view,
+            };
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("post-processing"),
+                color_attachments: &[wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: true,
+                    },
+                }],
+                depth_stencil_attachment: None,
+            });
+            rpass.set_pipeline(&self.stages[pass.stage].pipeline);
+            rpass.set_bind_group(0, &pass.bind_group, &[]);
+            rpass.draw(0..3, 0..1);
+        }
+    }
+}
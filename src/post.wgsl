@@ -0,0 +1,52 @@
+// What all of the post-processing effects share: a triangle that covers
+// the whole target, and the images that each stage reads.  The effect's
+// own shader is appended to this.
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] tex_coord: vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[builtin(vertex_index)]] index: u32,
+) -> VertexOutput {
+    // (0, 0), (2, 0), (0, 2): the corners of a triangle twice the size
+    // of the screen, which is clipped down to exactly cover it.
+    let uv: vec2<f32> = vec2<f32>(f32((index << 1u32) & 2u32), f32(index & 2u32));
+    var out: VertexOutput;
+    out.position = vec4<f32>(uv * 2.0 - vec2<f32>(1.0, 1.0), 0.0, 1.0);
+    // textures have y going down
+    out.tex_coord = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// Binding 0 is the effect's own parameters.
+[[group(0), binding(1)]] var sampler: sampler;
+// The output of the stage before.
+[[group(0), binding(2)]] var source: texture_2d<f32>;
+// What went into the effect, before its first stage.
+[[group(0), binding(3)]] var input: texture_2d<f32>;
+
+// The size of a pixel, in texture coordinates.
+fn texel() -> vec2<f32> {
+    let size: vec2<i32> = textureDimensions(source);
+    return vec2<f32>(1.0 / f32(size.x), 1.0 / f32(size.y));
+}
+
+// One direction of a separable gaussian blur of the source, reaching out
+// this many pixels either side.
+fn blur(tex_coord: vec2<f32>, direction: vec2<f32>, radius: f32) -> vec3<f32> {
+    let step: vec2<f32> = direction * texel() * max(radius, 1.0) / 4.0;
+    var sum: vec3<f32> = vec3<f32>(0.0, 0.0, 0.0);
+    var total: f32 = 0.0;
+    for (var i: i32 = -4; i <= 4; i = i + 1) {
+        let weight: f32 = exp(-f32(i * i) / 8.0);
+        let color: vec4<f32> = textureSample(source, sampler, tex_coord + step * f32(i));
+        sum = sum + color.rgb * weight;
+        total = total + weight;
+    }
+    return sum / total;
+}
+
+// ----------------------------------------------------------------------
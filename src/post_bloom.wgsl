@@ -0,0 +1,39 @@
+// Bloom: the bright parts of the picture are blurred and added back on,
+// so that they glow.
+
+[[block]]
+struct Bloom {
+    threshold: f32;     // how bright a pixel has to be to glow
+    strength: f32;
+    radius: f32;        // in pixels
+    pad0: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> bloom: Bloom;
+
+// Keep only what's brighter than the threshold.
+[[stage(fragment)]]
+fn fs_bright(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    let color: vec4<f32> = textureSample(source, sampler, in.tex_coord);
+    let t: f32 = bloom.threshold;
+    let bright: vec3<f32> = max(color.rgb - vec3<f32>(t, t, t), vec3<f32>(0.0, 0.0, 0.0));
+    return vec4<f32>(bright, 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_horizontal(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(blur(in.tex_coord, vec2<f32>(1.0, 0.0), bloom.radius), 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_vertical_add(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    let glow: vec3<f32> = blur(in.tex_coord, vec2<f32>(0.0, 1.0), bloom.radius);
+    let color: vec4<f32> = textureSample(input, sampler, in.tex_coord);
+    return vec4<f32>(color.rgb + glow * bloom.strength, 1.0);
+}
@@ -0,0 +1,25 @@
+// A gaussian blur, done across and then down.
+
+[[block]]
+struct Blur {
+    radius: f32;        // in pixels
+    pad0: f32;
+    pad1: f32;
+    pad2: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> params: Blur;
+
+[[stage(fragment)]]
+fn fs_horizontal(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(blur(in.tex_coord, vec2<f32>(1.0, 0.0), params.radius), 1.0);
+}
+
+[[stage(fragment)]]
+fn fs_vertical(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    return vec4<f32>(blur(in.tex_coord, vec2<f32>(0.0, 1.0), params.radius), 1.0);
+}
@@ -0,0 +1,39 @@
+// Edge detection, with a Sobel filter on the brightness.
+
+[[block]]
+struct Edges {
+    strength: f32;      // 1 shows only the edges
+    pad0: f32;
+    pad1: f32;
+    pad2: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> edges: Edges;
+
+fn luma(tex_coord: vec2<f32>, dx: f32, dy: f32) -> f32 {
+    let color: vec4<f32> = textureSample(source, sampler,
+        tex_coord + vec2<f32>(dx, dy) * texel());
+    return dot(color.rgb, vec3<f32>(0.299, 0.587, 0.114));
+}
+
+[[stage(fragment)]]
+fn fs_main(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    let tc: vec2<f32> = in.tex_coord;
+    let tl: f32 = luma(tc, -1.0, -1.0);
+    let t:  f32 = luma(tc,  0.0, -1.0);
+    let tr: f32 = luma(tc,  1.0, -1.0);
+    let l:  f32 = luma(tc, -1.0,  0.0);
+    let r:  f32 = luma(tc,  1.0,  0.0);
+    let bl: f32 = luma(tc, -1.0,  1.0);
+    let b:  f32 = luma(tc,  0.0,  1.0);
+    let br: f32 = luma(tc,  1.0,  1.0);
+    let gx: f32 = (tr + 2.0 * r + br) - (tl + 2.0 * l + bl);
+    let gy: f32 = (bl + 2.0 * b + br) - (tl + 2.0 * t + tr);
+    let edge: f32 = min(sqrt(gx * gx + gy * gy), 1.0);
+
+    let color: vec4<f32> = textureSample(source, sampler, tc);
+    return vec4<f32>(mix(color.rgb, color.rgb * edge + vec3<f32>(edge * 0.25),
+        edges.strength), 1.0);
+}
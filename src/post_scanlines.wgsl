@@ -0,0 +1,20 @@
+// Dark lines across the picture, like an old CRT.
+
+[[block]]
+struct Scanlines {
+    strength: f32;      // how dark the lines get, from 0 to 1
+    spacing: f32;       // in pixels
+    pad0: f32;
+    pad1: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> scanlines: Scanlines;
+
+[[stage(fragment)]]
+fn fs_main(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    let color: vec4<f32> = textureSample(source, sampler, in.tex_coord);
+    let line: f32 = 0.5 + 0.5 * cos(6.2831853 * in.position.y / max(scanlines.spacing, 1.0));
+    return vec4<f32>(color.rgb * (1.0 - scanlines.strength * line), 1.0);
+}
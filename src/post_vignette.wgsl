@@ -0,0 +1,22 @@
+// Darkening towards the corners.
+
+[[block]]
+struct Vignette {
+    strength: f32;      // how dark the corners get, from 0 to 1
+    radius: f32;        // where it starts, as a fraction of the way to the edge
+    pad0: f32;
+    pad1: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> vignette: Vignette;
+
+[[stage(fragment)]]
+fn fs_main(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    let color: vec4<f32> = textureSample(source, sampler, in.tex_coord);
+    let d: f32 = distance(in.tex_coord, vec2<f32>(0.5, 0.5)) * 2.0;
+    let t: f32 = clamp((d - vignette.radius) / (1.5 - vignette.radius), 0.0, 1.0);
+    let dark: f32 = t * t * (3.0 - 2.0 * t) * vignette.strength;
+    return vec4<f32>(color.rgb * (1.0 - dark), 1.0);
+}
@@ -0,0 +1,272 @@
+// Recording every rendered frame, either as a numbered sequence of PNG files
+// or as a YUV4MPEG2 stream that can be piped into a video encoder.
+//
+// Reading a frame back from the GPU takes a while, so each frame is copied
+// into one of a ring of staging buffers, and is only written out once that
+// buffer has been mapped.  The render loop only has to wait for the GPU if
+// every buffer in the ring is still in flight.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::future::Future;
+use std::io::{self, BufWriter, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::task::noop_waker_ref;
+use pollster::block_on;
+
+use crate::{
+    capture::CaptureTarget,
+    dimensions::Dimensions,
+    options::RecordFormat,
+    screenshot::write_png,
+};
+
+// How many frames can be in flight between the GPU and the output.
+const RING_SIZE: usize = 4;
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), wgpu::BufferAsyncError>>>>;
+
+struct Slot {
+    buffer: wgpu::Buffer,
+    frame_num: usize,
+    mapping: Option<MapFuture>,
+}
+
+enum Sink {
+    Png {
+        prefix: String,
+    },
+    Y4m {
+        out: Box<dyn Write>,
+        yuv: Vec<u8>,
+    },
+}
+
+pub struct Recorder {
+    target: CaptureTarget,
+    free: Vec<Slot>,
+    encoded: Option<Slot>,      // copied into, but not yet submitted
+    in_flight: VecDeque<Slot>,  // submitted, in the order they were rendered
+    sink: Sink,
+    recording: bool,
+    frames_written: usize,
+}
+
+impl Recorder {
+    pub fn new(
+        device: &wgpu::Device,
+        dim: Dimensions,
+        format: wgpu::TextureFormat,
+        path: &str,
+        record_format: RecordFormat,
+        fps: u32,
+    ) -> io::Result<Self> {
+        let sink = match record_format {
+            RecordFormat::Png => Sink::Png {
+                prefix: path.to_string(),
+            },
+            RecordFormat::Y4m => {
+                let mut out: Box<dyn Write> = if path == "-" {
+                    Box::new(BufWriter::new(io::stdout()))
+                } else {
+                    Box::new(BufWriter::new(File::create(path)?))
+                };
+                writeln!(out, "YUV4MPEG2 W{} H{} F{}:1 Ip A1:1 C444",
+                    dim.width(), dim.height(), fps)?;
+                Sink::Y4m {
+                    out,
+                    yuv: vec![0; dim.area() * 3],
+                }
+            },
+        };
+
+        let target = CaptureTarget::new(device, dim, format);
+        let free = (0..RING_SIZE).map(|i| Slot {
+            buffer: target.create_buffer(device,
+                &format!("recorder staging buffer {}", i)),
+            frame_num: 0,
+            mapping: None,
+        }).collect();
+
+        Ok(Recorder {
+            target,
+            free,
+            encoded: None,
+            in_flight: VecDeque::new(),
+            sink,
+            recording: false,
+            frames_written: 0,
+        })
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording
+    }
+
+    pub fn start(&mut self) {
+        log::info!("recorder: starting");
+        self.recording = true;
+    }
+
+    // Stop recording, and write out any frames that are still in flight.
+    pub fn stop(
+        &mut self,
+        device: &wgpu::Device,
+    ) {
+        while !self.in_flight.is_empty() {
+            self.wait_oldest(device);
+        }
+        if let Sink::Y4m { out, .. } = &mut self.sink {
+            if let Err(e) = out.flush() {
+                log::warn!("recorder: flush failed: {}", e);
+            }
+        }
+        if self.recording {
+            log::info!("recorder: stopped after {} frames", self.frames_written);
+        }
+        self.recording = false;
+    }
+
+    // Render the current frame into a staging buffer, with a function that
+    // draws what the window shows into a view of the given size.
+    // This must be followed by submitted() once the encoder has been submitted.
+    pub fn enqueue(
+        &mut self,
+        device: &wgpu::Device,
+        command_encoder: &mut wgpu::CommandEncoder,
+        frame_num: usize,
+        render: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView, Dimensions),
+    ) {
+        if !self.recording {
+            return;
+        }
+        if self.free.is_empty() {
+            self.wait_oldest(device);
+        }
+        let mut slot = self.free.pop().expect("no free recorder buffer");
+        slot.frame_num = frame_num;
+
+        render(command_encoder, self.target.view(), self.target.dim());
+        self.target.enqueue_copyout(command_encoder, &slot.buffer);
+        self.encoded = Some(slot);
+    }
+
+    // Start mapping the buffer that enqueue() filled in.
+    pub fn submitted(&mut self) {
+        if let Some(mut slot) = self.encoded.take() {
+            slot.mapping = Some(Box::pin(
+                slot.buffer.slice(..).map_async(wgpu::MapMode::Read)));
+            self.in_flight.push_back(slot);
+        }
+    }
+
+    // Write out any frames whose buffers have finished mapping,
+    // without waiting for the rest.
+    pub fn write_ready(
+        &mut self,
+        device: &wgpu::Device,
+    ) {
+        device.poll(wgpu::Maintain::Poll);
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        while let Some(slot) = self.in_flight.front_mut() {
+            let mapping = slot.mapping.as_mut().expect("slot isn't mapping");
+            match mapping.as_mut().poll(&mut cx) {
+                Poll::Pending => break,
+                Poll::Ready(result) => {
+                    let slot = self.in_flight.pop_front().unwrap();
+                    self.finish(slot, result);
+                },
+            }
+        }
+    }
+
+    fn wait_oldest(
+        &mut self,
+        device: &wgpu::Device,
+    ) {
+        if let Some(mut slot) = self.in_flight.pop_front() {
+            device.poll(wgpu::Maintain::Wait);
+            let mapping = slot.mapping.take().expect("slot isn't mapping");
+            let result = block_on(mapping);
+            self.finish(slot, result);
+        }
+    }
+
+    // Write out a mapped buffer, and put it back in the free list.
+    fn finish(
+        &mut self,
+        mut slot: Slot,
+        result: Result<(), wgpu::BufferAsyncError>,
+    ) {
+        slot.mapping = None;
+        match result {
+            Err(e) => {
+                log::warn!("recorder: failed to map frame {}: {}",
+                    slot.frame_num, e);
+            }
+            Ok(_) => {
+                let rgba = self.target.to_rgba(
+                    &slot.buffer.slice(..).get_mapped_range());
+                slot.buffer.unmap();
+
+                if let Err(e) = self.write_frame(slot.frame_num, &rgba) {
+                    log::warn!("recorder: failed to write frame {}: {}",
+                        slot.frame_num, e);
+                    self.recording = false;
+                } else {
+                    self.frames_written += 1;
+                }
+            }
+        }
+        self.free.push(slot);
+    }
+
+    fn write_frame(
+        &mut self,
+        frame_num: usize,
+        rgba: &[u8],
+    ) -> io::Result<()> {
+        let dim = self.target.dim();
+        match &mut self.sink {
+            Sink::Png { prefix } => {
+                let path = format!("{}-{:06}.png", prefix, frame_num);
+                let mut file = BufWriter::new(File::create(&path)?);
+                write_png(&mut file, dim, rgba)?;
+                file.flush()
+            },
+            Sink::Y4m { out, yuv } => {
+                rgba_to_yuv444(rgba, yuv);
+                out.write_all(b"FRAME\n")?;
+                out.write_all(yuv)
+            },
+        }
+    }
+}
+
+// Convert RGBA pixels to planar 4:4:4 Y'CbCr, using the BT.601 coefficients
+// and studio-swing ranges that encoders assume for y4m input.
+fn rgba_to_yuv444(
+    rgba: &[u8],
+    yuv: &mut [u8],
+) {
+    let n = rgba.len() / 4;
+    let (y_plane, rest) = yuv.split_at_mut(n);
+    let (u_plane, v_plane) = rest.split_at_mut(n);
+
+    for (i, px) in rgba.chunks_exact(4).enumerate() {
+        let r = px[0] as f32 / 255.0;
+        let g = px[1] as f32 / 255.0;
+        let b = px[2] as f32 / 255.0;
+
+        let y =  16.0 + ( 65.481 * r + 128.553 * g +  24.966 * b);
+        let u = 128.0 + (-37.797 * r -  74.203 * g + 112.000 * b);
+        let v = 128.0 + (112.000 * r -  93.786 * g -  18.214 * b);
+
+        y_plane[i] = y.round() as u8;
+        u_plane[i] = u.round() as u8;
+        v_plane[i] = v.round() as u8;
+    }
+}
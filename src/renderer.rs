@@ -7,17 +7,98 @@ use std::{borrow::Cow, mem};
 use wgpu::util::DeviceExt;
 
 use crate::{
-    bindable::{Bindable, BindAccess, Sampler},
+    bindable::{Bindable, BindAccess, Buffer, BufferType, Sampler},
+    camera::{Camera, CameraUniform},
+    colormap::{Colormap, PaletteTexture},
+    dimensions::{Dimensions, Rect},
 };
 
+// Things that are drawn over the cells: the selected rectangle, a ghost
+// of the clipboard showing where it would be pasted, and grid lines.
+// The trail is drawn under them, where cells are dead, and cells can be
+// colored by age, with newborn and recently dead cells picked out.
+// This must match the Overlay struct in renderer.wgsl.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Pod, Zeroable)]
+pub struct Overlay {
+    selection: [i32; 4],    // x, y, width, height; no width means none
+    ghost: [i32; 2],        // the bottom left corner of the ghost
+    clip_dim: [u32; 2],     // the clipboard, before it's turned around
+    orient: u32,            // as in Orientation::bits()
+    show_ghost: u32,
+    show_grid: u32,
+    cell_size: f32,         // in pixels
+    show_trail: u32,
+    color_by_age: u32,
+    show_changes: u32,      // newborn and recently dead cells
+    _pad: u32,
+}
+
+impl Overlay {
+    pub fn select(
+        &mut self,
+        rect: Rect,
+    ) {
+        self.selection = [rect.x, rect.y, rect.width as i32, rect.height as i32];
+    }
+
+    pub fn ghost(
+        &mut self,
+        origin: (i32, i32),
+        clip_dim: Dimensions,
+        orient: u32,
+    ) {
+        self.ghost = [origin.0, origin.1];
+        self.clip_dim = [clip_dim.width(), clip_dim.height()];
+        self.orient = orient;
+        self.show_ghost = 1;
+    }
+
+    // Color in where cells have been, from the trail.
+    pub fn trail(&mut self) {
+        self.show_trail = 1;
+    }
+
+    // Color live cells by how long they've been alive, not by their value.
+    pub fn color_by_age(&mut self) {
+        self.color_by_age = 1;
+    }
+
+    // Pick out the cells that have just been born or just died.
+    pub fn changes(&mut self) {
+        self.show_changes = 1;
+    }
+
+    // Draw lines between the cells, which are this many pixels wide.
+    pub fn grid_lines(
+        &mut self,
+        cell_size: f32,
+    ) {
+        self.show_grid = 1;
+        self.cell_size = cell_size;
+    }
+
+    pub fn has_grid_lines(&self) -> bool {
+        self.show_grid != 0
+    }
+}
+
 pub struct Renderer {
     shader: wgpu::ShaderModule,
-    sampler: Sampler,
+    linear_sampler: Sampler,
+    nearest_sampler: Sampler,
+    camera: Buffer,
+    overlay: Buffer,
+    palette: PaletteTexture,
+    trail_palette: PaletteTexture,
     vertex_buf: wgpu::Buffer,
     index_buf: wgpu::Buffer,
     index_count: usize,
-    bind_group: wgpu::BindGroup,
+    linear_bind_group: wgpu::BindGroup,
+    nearest_bind_group: wgpu::BindGroup,
+    nearest: bool,      // whether cells are bigger than pixels
     pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
 }
 
 #[repr(C)]
@@ -53,36 +134,38 @@ impl Renderer {
         (vertex_data.to_vec(), index_data.to_vec())
     }
 
+    // Takes one set of arguments for each bind group wanted;
+    // they must all have the same layout.
     fn bind_up(
-        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
         device: &wgpu::Device,
         shader: &wgpu::ShaderModule,
-        bind_args: Vec<&dyn Bindable>,
-    ) -> (wgpu::BindGroup, wgpu::RenderPipeline) {
-        // Bind the fragment shader's arguments using a bind group.
-        // (The vertex shader doesn't need any arguments.)
+        bind_args: &[Vec<&dyn Bindable>],
+    ) -> (Vec<wgpu::BindGroup>, wgpu::RenderPipeline) {
+        // Bind the shaders' arguments using a bind group.
         let bind_group_layout = device.create_bind_group_layout(
             &wgpu::BindGroupLayoutDescriptor {
-                entries: &bind_args.iter().enumerate().map(|(idx, arg)| {
+                entries: &bind_args[0].iter().enumerate().map(|(idx, arg)| {
                     wgpu::BindGroupLayoutEntry {
                         binding: idx as _,
-                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        visibility: wgpu::ShaderStages::VERTEX
+                                  | wgpu::ShaderStages::FRAGMENT,
                         ty: arg.binding_type(BindAccess::ReadSampled),
                         count: None,
                     }}).collect::<Vec<_>>(),
                 label: None,
             });
 
-        let bind_group = device.create_bind_group(
+        let bind_groups = bind_args.iter().map(|args| device.create_bind_group(
             &wgpu::BindGroupDescriptor {
                 layout: &bind_group_layout,
-                entries: &bind_args.iter().enumerate().map(|(idx, arg)| {
+                entries: &args.iter().enumerate().map(|(idx, arg)| {
                     wgpu::BindGroupEntry {
                         binding: idx as _,
                         resource: arg.binding_resource(),
                     }}).collect::<Vec<_>>(),
                 label: None,
-            });
+            })).collect();
 
         // Create the render pipeline.
         let vertex_buffers = [wgpu::VertexBufferLayout {
@@ -120,7 +203,7 @@ impl Renderer {
                 fragment: Some(wgpu::FragmentState {
                     module: &shader,
                     entry_point: "fs_main",
-                    targets: &[config.format.into()],
+                    targets: &[format.into()],
                 }),
                 primitive: wgpu::PrimitiveState {
                     cull_mode: Some(wgpu::Face::Back),
@@ -130,14 +213,46 @@ impl Renderer {
                 multisample: wgpu::MultisampleState::default(),
             });
 
-        (bind_group, pipeline)
+        (bind_groups, pipeline)
+    }
+
+    // Bind up the shader arguments, once for each sampler.
+    // The arguments are params, texture, camera, overlay, clipboard, palette,
+    // trail, trail palette and ages; the sampler goes in between the texture
+    // and the camera.
+    fn bind_all(
+        format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        [params, texture, camera, overlay, clip, palette, trail, trail_palette, ages]:
+            [&dyn Bindable; 9],
+        [linear_sampler, nearest_sampler]: [&Sampler; 2],
+    ) -> (wgpu::BindGroup, wgpu::BindGroup, wgpu::RenderPipeline) {
+        let (mut bind_groups, pipeline) = Renderer::bind_up(
+            format,
+            device,
+            shader,
+            &[
+                vec![ params, texture, linear_sampler, camera, overlay, clip,
+                    palette, trail, trail_palette, ages ],
+                vec![ params, texture, nearest_sampler, camera, overlay, clip,
+                    palette, trail, trail_palette, ages ],
+            ],
+        );
+        let nearest_bind_group = bind_groups.pop().unwrap();
+        let linear_bind_group = bind_groups.pop().unwrap();
+
+        (linear_bind_group, nearest_bind_group, pipeline)
     }
 
     pub fn new(
-        config: &wgpu::SurfaceConfiguration,
+        format: wgpu::TextureFormat,
         device: &wgpu::Device,
         params: &dyn Bindable,
         texture: &dyn Bindable,
+        trail: &dyn Bindable,
+        ages: &dyn Bindable,
+        clip: &dyn Bindable,
     ) -> Self {
         // Load and compile the shaders.
         let shader = device.create_shader_module(
@@ -162,53 +277,168 @@ impl Renderer {
                 usage: wgpu::BufferUsages::INDEX,
             });
 
-        // Create a sampler.
-        let sampler = Sampler::new(&device,
+        // Create the samplers.  The nearest-neighbor one is used when
+        // the view is zoomed in far enough that cells are bigger than pixels.
+        let linear_sampler = Sampler::new(&device,
             wgpu::AddressMode::Repeat,
             wgpu::FilterMode::Linear,
         );
+        let nearest_sampler = Sampler::new(&device,
+            wgpu::AddressMode::Repeat,
+            wgpu::FilterMode::Nearest,
+        );
+
+        // Create the camera parameters.  These are set before each frame;
+        // to begin with, the whole grid fills the view.
+        let whole = Dimensions::new(1, 1);
+        let camera = Buffer::new_init(
+            device,
+            "Camera parameters",
+            BufferType::Uniform,
+            bytemuck::bytes_of(&Camera::new(whole, whole).uniform()),
+        );
 
-        let (bind_group, pipeline) = Renderer::bind_up(
-            config,
+        // Create the overlay parameters; there's nothing to show yet.
+        let overlay = Buffer::new_init(
             device,
-            &shader,
-            vec![ params, texture, &sampler ],
+            "Overlay parameters",
+            BufferType::Uniform,
+            bytemuck::bytes_of(&Overlay::default()),
         );
 
+        // The colormaps are filled in by set_colormap().
+        let palette = PaletteTexture::new(device);
+        let trail_palette = PaletteTexture::new(device);
+
+        let (linear_bind_group, nearest_bind_group, pipeline) =
+            Renderer::bind_all(format, device, &shader,
+                [params, texture, &camera, &overlay, clip, &palette,
+                    trail, &trail_palette, ages],
+                [&linear_sampler, &nearest_sampler]);
+
         // Done.
         Renderer {
             shader,
-            sampler,
+            linear_sampler,
+            nearest_sampler,
+            camera,
+            overlay,
+            palette,
+            trail_palette,
             vertex_buf,
             index_buf,
             index_count: index_data.len(),
-            bind_group,
+            linear_bind_group,
+            nearest_bind_group,
+            nearest: false,
             pipeline,
+            format,
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn resize(
         &mut self,
         config: &wgpu::SurfaceConfiguration,
         device: &wgpu::Device,
         params: &dyn Bindable,
         texture: &dyn Bindable,
+        trail: &dyn Bindable,
+        ages: &dyn Bindable,
+        clip: &dyn Bindable,
     ) {
-        let (bind_group, pipeline) = Renderer::bind_up(
-            config,
-            device,
-            &self.shader,
-            vec![ params, texture, &self.sampler ],
-        );
+        self.format = config.format;
+        self.rebind(device, params, texture, trail, ages, clip);
+    }
+
+    // Bind to a different set of arguments, such as after the clipboard
+    // has been replaced.
+    pub fn rebind(
+        &mut self,
+        device: &wgpu::Device,
+        params: &dyn Bindable,
+        texture: &dyn Bindable,
+        trail: &dyn Bindable,
+        ages: &dyn Bindable,
+        clip: &dyn Bindable,
+    ) {
+        let (linear_bind_group, nearest_bind_group, pipeline) =
+            Renderer::bind_all(self.format, device, &self.shader,
+                [params, texture, &self.camera, &self.overlay, clip,
+                    &self.palette, trail, &self.trail_palette, ages],
+                [&self.linear_sampler, &self.nearest_sampler]);
 
-        self.bind_group = bind_group;
+        self.linear_bind_group = linear_bind_group;
+        self.nearest_bind_group = nearest_bind_group;
         self.pipeline = pipeline;
     }
 
+    // Point the view at a different part of the grid.
+    pub fn set_camera(
+        &mut self,
+        queue: &wgpu::Queue,
+        camera: &Camera,
+    ) {
+        let uniform: CameraUniform = camera.uniform();
+        queue.write_buffer(self.camera.buf(), 0, bytemuck::bytes_of(&uniform));
+        self.nearest = camera.cell_size() > 1.0;
+    }
+
+    // Change what's drawn over the cells.
+    pub fn set_overlay(
+        &self,
+        queue: &wgpu::Queue,
+        overlay: &Overlay,
+    ) {
+        queue.write_buffer(self.overlay.buf(), 0, bytemuck::bytes_of(overlay));
+    }
+
+    // Change how cells are colored.
+    pub fn set_colormap(
+        &self,
+        queue: &wgpu::Queue,
+        colormap: &Colormap,
+    ) {
+        self.palette.set(queue, colormap);
+    }
+
+    // Change how the trail is colored.
+    pub fn set_trail_colormap(
+        &self,
+        queue: &wgpu::Queue,
+        colormap: &Colormap,
+    ) {
+        self.trail_palette.set(queue, colormap);
+    }
+
+    // The format of the textures that render() can draw into.
+    pub fn format(&self) -> wgpu::TextureFormat {
+        self.format
+    }
+
     pub fn render(
         &mut self,
         encoder: &mut wgpu::CommandEncoder,
         view: &wgpu::TextureView,
+    ) {
+        self.draw(encoder, view, None);
+    }
+
+    // Draw into one part of the view, leaving the rest of it as it was.
+    pub fn render_viewport(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        viewport: Rect,
+    ) {
+        self.draw(encoder, view, Some(viewport));
+    }
+
+    fn draw(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        viewport: Option<Rect>,
     ) {
         let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: None,
@@ -216,15 +446,26 @@ impl Renderer {
                 view,
                 resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: match viewport {
+                        Some(_) => wgpu::LoadOp::Load,
+                        None => wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    },
                     store: true,
                 },
             }],
             depth_stencil_attachment: None,
         });
+        if let Some(rect) = viewport {
+            rpass.set_viewport(rect.x as f32, rect.y as f32,
+                rect.width as f32, rect.height as f32, 0.0, 1.0);
+        }
         rpass.push_debug_group("Prepare data for draw.");
         rpass.set_pipeline(&self.pipeline);
-        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_bind_group(0, if self.nearest {
+            &self.nearest_bind_group
+        } else {
+            &self.linear_bind_group
+        }, &[]);
         rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint16);
         rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
         rpass.pop_debug_group();
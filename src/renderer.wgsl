@@ -1,8 +1,17 @@
 struct VertexOutput {
     [[builtin(position)]] position: vec4<f32>;
     [[location(0)]] tex_coord: vec2<f32>;
+    [[location(1)]] offset: vec2<f32>;  // from the center, in grids
 };
 
+[[block]]
+struct Camera {
+    center: vec2<f32>;
+    extent: vec2<f32>;      // how much of the grid the window spans
+};
+
+[[group(0), binding(3)]] var<uniform> camera: Camera;
+
 [[stage(vertex)]]
 fn vs_main(
     [[location(0)]] position: vec4<f32>,
@@ -10,55 +19,36 @@ fn vs_main(
 ) -> VertexOutput {
     var out: VertexOutput;
     out.position = position;
-    out.tex_coord = tex_coord;
+    out.offset = (tex_coord - vec2<f32>(0.5, 0.5)) * camera.extent;
+    // the sampler wraps around, so this can go outside of 0..1
+    out.tex_coord = camera.center + out.offset;
     return out;
 }
 
-fn hsv_to_rgb(hsv: vec3<f32>) -> vec3<f32> {
-    let h: f32 = hsv.x * 6.0f;
-    let s: f32 = hsv.y;
-    let v: f32 = hsv.z;
-
-    let w: i32 = i32(h);
-    let f: f32 = h - f32(w);
-    let p: f32 = v * (1.0f - s);
-    let q: f32 = v * (1.0f - (s * f));
-    let t: f32 = v * (1.0f - (s * (1.0f - f)));
-
-    var r: f32;
-    var g: f32;
-    var b: f32;
-
-    // workaround for Naga bugs listed below
-    r = v; g = t; b = p;
-
-    switch (w) {
-        // "default: fallthrough;" doesn't work right now
-        // https://github.com/gfx-rs/naga/issues/1088
-        // "case 6: { fallthrough; }" also doesn't work right now
-        // https://github.com/gfx-rs/naga/issues/1099
-        case 0: { r = v; g = t; b = p; }
-        case 1: { r = q; g = v; b = p; }
-        case 2: { r = p; g = v; b = t; }
-        case 3: { r = p; g = q; b = v; }
-        case 4: { r = t; g = p; b = v; }
-        case 5: { r = v; g = p; b = q; }
-    }
-
-    return vec3<f32>(r, g, b);
-}
-
 [[block]]
 struct LifeParams {
     width: u32;
     height: u32;
     threshold: f32;
+    birth: u32;
+    survive: u32;
 };
 
 [[group(0), binding(0)]] var<uniform> params: LifeParams;
 [[group(0), binding(1)]] var texture: texture_2d<f32>;
 [[group(0), binding(2)]] var sampler: sampler;
 
+// The colormap, from barely alive to fully alive.
+[[group(0), binding(6)]] var palette: texture_1d<f32>;
+
+// The color a fraction a of the way along the colormap.
+fn palette_color(a: f32) -> vec3<f32> {
+    let size: i32 = textureDimensions(palette);
+    let i: i32 = i32(a * f32(size - 1) + 0.5f);
+    let color: vec4<f32> = textureLoad(palette, i, 0);
+    return color.rgb;
+}
+
 fn render(val: f32) -> vec3<f32> {
     let thresh: f32 = params.threshold;
 
@@ -66,10 +56,177 @@ fn render(val: f32) -> vec3<f32> {
     if (val <= thresh) {
         return vec3<f32>(0f, 0f, 0f);
     } else {
-        let a: f32 = (val - thresh) / (1.0f - thresh);
-        let b: f32 = (1.0f - a) * 0.7f;
-        return hsv_to_rgb(vec3<f32>(b, 1.0f, 1.0f));
+        let a: f32 = min((val - thresh) / (1.0f - thresh), 1.0f);
+        return palette_color(a);
+    }
+}
+
+// ----------------------------------------------------------------------
+// The selection, the clipboard ghost and the grid lines, drawn over the cells.
+
+[[block]]
+struct Overlay {
+    selection: vec4<i32>;   // x, y, width, height; no width means none
+    ghost: vec2<i32>;       // the bottom left corner of the ghost
+    clip_dim: vec2<u32>;    // the clipboard, before it's turned around
+    orient: u32;            // bits 0-1: quarter turns; bit 2: flip x; bit 3: flip y
+    show_ghost: u32;
+    show_grid: u32;
+    cell_size: f32;         // in pixels
+    show_trail: u32;
+    color_by_age: u32;
+    show_changes: u32;      // newborn and recently dead cells
+    pad0: u32;
+};
+
+[[block]]
+struct Cells {
+    cells: array<f32>;
+};
+
+[[group(0), binding(4)]] var<uniform> overlay: Overlay;
+[[group(0), binding(5)]] var<storage, read> clip: Cells;
+
+// Where a cell is relative to an origin, allowing for the grid wrapping.
+fn relative(cell: vec2<i32>, origin: vec2<i32>) -> vec2<i32> {
+    let size: vec2<i32> = vec2<i32>(i32(params.width), i32(params.height));
+    return ((cell - origin) % size + size) % size;
+}
+
+// The value of the clipboard cell that would be pasted at a position
+// relative to the ghost's corner, or -1 if it's outside the clipboard.
+// This works the same way as clip_index() in clipboard.wgsl.
+fn ghost_value(rel: vec2<i32>) -> f32 {
+    let cw: u32 = overlay.clip_dim.x;
+    let ch: u32 = overlay.clip_dim.y;
+    var size: vec2<u32> = vec2<u32>(cw, ch);
+    if ((overlay.orient & 1u32) != 0u32) {
+        size = vec2<u32>(ch, cw);
+    }
+    var p: vec2<u32> = vec2<u32>(rel);
+    if (p.x >= size.x || p.y >= size.y) {
+        return -1.0;
+    }
+
+    if ((overlay.orient & 4u32) != 0u32) {
+        p.x = size.x - 1u32 - p.x;
+    }
+    if ((overlay.orient & 8u32) != 0u32) {
+        p.y = size.y - 1u32 - p.y;
+    }
+    var c: vec2<u32> = p;
+    let turns: u32 = overlay.orient & 3u32;
+    if (turns == 1u32) {
+        c = vec2<u32>(p.y, ch - 1u32 - p.x);
+    }
+    if (turns == 2u32) {
+        c = vec2<u32>(cw - 1u32 - p.x, ch - 1u32 - p.y);
     }
+    if (turns == 3u32) {
+        c = vec2<u32>(cw - 1u32 - p.y, p.x);
+    }
+    return clip.cells[c.y * cw + c.x];
+}
+
+fn draw_overlay(rgb: vec3<f32>, tex_coord: vec2<f32>) -> vec3<f32> {
+    let grid: vec2<f32> = vec2<f32>(f32(params.width), f32(params.height));
+    let cell: vec2<i32> = vec2<i32>(floor(fract(tex_coord) * grid));
+    var out: vec3<f32> = rgb;
+
+    if (overlay.selection.z > 0) {
+        let rel: vec2<i32> = relative(cell, overlay.selection.xy);
+        if (rel.x < overlay.selection.z && rel.y < overlay.selection.w) {
+            out = mix(out, vec3<f32>(0.3, 0.5, 1.0), 0.35);
+        }
+    }
+
+    if (overlay.show_ghost != 0u32) {
+        let value: f32 = ghost_value(relative(cell, overlay.ghost));
+        if (value > params.threshold) {
+            out = mix(out, vec3<f32>(1.0, 1.0, 1.0), 0.6);
+        } else {
+            if (value >= 0.0) {
+                out = mix(out, vec3<f32>(1.0, 1.0, 1.0), 0.1);
+            }
+        }
+    }
+
+    // Lines between the cells, if they're at least 4 pixels wide.
+    if (overlay.show_grid != 0u32 && overlay.cell_size >= 4.0) {
+        let inside: vec2<f32> = fract(fract(tex_coord) * grid) * overlay.cell_size;
+        if (inside.x < 1.0 || inside.y < 1.0) {
+            out = mix(out, vec3<f32>(0.3, 0.3, 0.3), 0.5);
+        }
+    }
+
+    return out;
+}
+
+// ----------------------------------------------------------------------
+// The trail of where cells have been alive, shown where they're dead.
+
+[[group(0), binding(7)]] var trail: texture_2d<f32>;
+[[group(0), binding(8)]] var trail_palette: texture_1d<f32>;
+
+fn draw_trail(rgb: vec3<f32>, value: f32, tex_coord: vec2<f32>) -> vec3<f32> {
+    if (overlay.show_trail == 0u32 || value > params.threshold) {
+        return rgb;
+    }
+    let level: f32 = textureSample(trail, sampler, tex_coord).x;
+    if (level < 1.0 / 256.0) {
+        return rgb;
+    }
+    let size: i32 = textureDimensions(trail_palette);
+    let i: i32 = i32(min(level, 1.0) * f32(size - 1) + 0.5);
+    let color: vec4<f32> = textureLoad(trail_palette, i, 0);
+    return color.rgb;
+}
+
+// ----------------------------------------------------------------------
+// How long cells have been alive, or dead, as counted by life.wgsl.
+
+[[block]]
+struct Ages {
+    ages: array<vec2<u32>>;     // generations alive, generations since death
+};
+
+[[group(0), binding(9)]] var<storage, read> ages: Ages;
+
+fn age_at(tex_coord: vec2<f32>) -> vec2<u32> {
+    let grid: vec2<f32> = vec2<f32>(f32(params.width), f32(params.height));
+    let cell: vec2<u32> = min(vec2<u32>(floor(fract(tex_coord) * grid)),
+        vec2<u32>(params.width - 1u32, params.height - 1u32));
+    return ages.ages[cell.y * params.width + cell.x];
+}
+
+// Live cells go along the colormap as they get older.  This goes by the
+// log of the age, so that it takes 1024 generations to reach the end.
+fn draw_age(rgb: vec3<f32>, tex_coord: vec2<f32>) -> vec3<f32> {
+    if (overlay.color_by_age == 0u32) {
+        return rgb;
+    }
+    let age: vec2<u32> = age_at(tex_coord);
+    if (age.x == 0u32) {
+        return rgb;
+    }
+    return palette_color(min(log2(f32(age.x)) / 10.0, 1.0));
+}
+
+// Newborn cells are white, and cells that have died in the last eight
+// generations are a fading red.
+fn draw_changes(rgb: vec3<f32>, tex_coord: vec2<f32>) -> vec3<f32> {
+    if (overlay.show_changes == 0u32) {
+        return rgb;
+    }
+    let age: vec2<u32> = age_at(tex_coord);
+    if (age.x == 1u32) {
+        return vec3<f32>(1.0, 1.0, 1.0);
+    }
+    if (age.y > 0u32 && age.y <= 8u32) {
+        let fade: f32 = f32(age.y - 1u32) / 8.0;
+        return mix(vec3<f32>(0.9, 0.1, 0.1), rgb, fade);
+    }
+    return rgb;
 }
 
 [[stage(fragment)]]
@@ -77,6 +234,14 @@ fn fs_main(
     in: VertexOutput
 ) -> [[location(0)]] vec4<f32> {
     let value: f32 = textureSample(texture, sampler, in.tex_coord).x;
-    let rgb: vec3<f32> = render(value);
+    let aged: vec3<f32> = draw_age(render(value), in.tex_coord);
+    let cell: vec3<f32> = draw_trail(aged, value, in.tex_coord);
+    let rgb: vec3<f32> = draw_overlay(draw_changes(cell, in.tex_coord), in.tex_coord);
+
+    // When the window is wider or taller than the grid, only one copy
+    // of the grid is shown, with black around it.
+    if (abs(in.offset.x) > 0.5 || abs(in.offset.y) > 0.5) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
     return vec4<f32>(rgb.x, rgb.y, rgb.z, 1.0);
 }
@@ -0,0 +1,268 @@
+// Reading and writing patterns in the run-length encoded format that most
+// Life software uses.  See https://conwaylife.com/wiki/Run_Length_Encoded
+//
+// The rule in the header line is ignored; a pattern is just cells.
+
+use crate::{
+    dimensions::Dimensions,
+    rule::Rule,
+};
+
+// RLE files are supposed to keep their lines shorter than this.
+const LINE_LENGTH: usize = 70;
+
+pub struct Pattern {
+    pub name: String,
+    pub dim: Dimensions,
+    pub cells: Vec<f32>,    // row 0 is the bottom row, as in the grid
+}
+
+impl Pattern {
+    // Parse the text of an RLE file.  The name from a "#N" line is used
+    // if there is one, and the given name otherwise.  Patterns can't be
+    // more than max_side cells across or down.
+    pub fn parse(
+        text: &str,
+        name: &str,
+        max_side: u32,
+    ) -> Result<Pattern, String> {
+        let mut name = name.to_string();
+        let mut header: Option<(usize, usize)> = None;
+        let mut rows: Vec<Vec<bool>> = vec![Vec::new()];
+        let mut count = String::new();
+        let mut done = false;
+
+        for line in text.lines() {
+            let line = line.trim();
+            if done {
+                break;
+            }
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some(n) = comment.strip_prefix('N') {
+                    name = n.trim().to_string();
+                }
+                continue;
+            }
+            if header.is_none() {
+                header = Some(Pattern::parse_header(line, max_side)?);
+                continue;
+            }
+
+            for c in line.chars() {
+                if c.is_ascii_digit() {
+                    count.push(c);
+                    continue;
+                }
+                if c.is_whitespace() {
+                    continue;
+                }
+                let n: usize = if count.is_empty() {
+                    1
+                } else {
+                    count.parse().map_err(|_| format!("bad count {}", count))?
+                };
+                count.clear();
+
+                // The header comes first, so the cells can be checked
+                // against it as they're read.
+                let (width, height) = header.unwrap();
+                let too_big = || format!("pattern is bigger than {}x{}", width, height);
+                match c {
+                    '!' => {
+                        done = true;
+                        break;
+                    },
+                    '$' => {
+                        if n > height - rows.len() {
+                            return Err(too_big());
+                        }
+                        for _ in 0..n {
+                            rows.push(Vec::new());
+                        }
+                    },
+                    // b and . are dead; anything else is some kind of alive.
+                    _ => {
+                        let alive = c != 'b' && c != '.';
+                        let row = rows.last_mut().unwrap();
+                        if n > width - row.len() {
+                            return Err(too_big());
+                        }
+                        row.extend(std::iter::repeat(alive).take(n));
+                    },
+                }
+            }
+        }
+
+        let (width, height) = header.ok_or("no header line")?;
+
+        // RLE goes from the top down, but the grid goes from the bottom up.
+        let mut cells = vec![0.0; width * height];
+        for (y, row) in rows.iter().enumerate() {
+            let base = (height - 1 - y) * width;
+            for (x, &alive) in row.iter().enumerate() {
+                if alive {
+                    cells[base + x] = 1.0;
+                }
+            }
+        }
+
+        Ok(Pattern {
+            name,
+            dim: Dimensions::new(width as u32, height as u32),
+            cells,
+        })
+    }
+
+    // Get the size out of a line like "x = 3, y = 3, rule = B3/S23",
+    // before anything that size gets made.
+    fn parse_header(
+        line: &str,
+        max_side: u32,
+    ) -> Result<(usize, usize), String> {
+        let mut width = None;
+        let mut height = None;
+        for field in line.split(',') {
+            let mut parts = field.splitn(2, '=');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            match key {
+                "x" => width = value.parse().ok(),
+                "y" => height = value.parse().ok(),
+                _ => {},
+            }
+        }
+        match (width, height) {
+            (Some(w), Some(h)) if w > max_side as usize || h > max_side as usize =>
+                Err(format!("{}x{} is bigger than the largest pattern, {}x{}",
+                    w, h, max_side, max_side)),
+            (Some(w), Some(h)) if w > 0 && h > 0 => Ok((w, h)),
+            _ => Err(format!("bad header line: {}", line)),
+        }
+    }
+
+    // Write the pattern out as RLE, counting cells above the threshold
+    // as alive.
+    pub fn to_rle(
+        &self,
+        rule: Rule,
+        threshold: f32,
+    ) -> String {
+        let width = self.dim.width() as usize;
+        let height = self.dim.height() as usize;
+
+        // Runs of cells, going from the top down; trailing dead cells
+        // in a row are left off, and so are trailing empty rows.
+        let mut tokens: Vec<String> = Vec::new();
+        let mut newlines = 0;
+        let push = |tokens: &mut Vec<String>, n: usize, c: char| {
+            tokens.push(if n == 1 { c.to_string() } else { format!("{}{}", n, c) });
+        };
+        for y in (0..height).rev() {
+            let row = &self.cells[(y * width)..((y + 1) * width)];
+            let mut runs: Vec<(usize, bool)> = Vec::new();
+            for &cell in row {
+                let alive = cell > threshold;
+                match runs.last_mut() {
+                    Some((n, a)) if *a == alive => *n += 1,
+                    _ => runs.push((1, alive)),
+                }
+            }
+            if let Some((_, false)) = runs.last() {
+                runs.pop();
+            }
+            if !runs.is_empty() {
+                if newlines > 0 {
+                    push(&mut tokens, newlines, '$');
+                    newlines = 0;
+                }
+                for (n, alive) in runs {
+                    push(&mut tokens, n, if alive { 'o' } else { 'b' });
+                }
+            }
+            newlines += 1;
+        }
+        tokens.push("!".to_string());
+
+        let mut text = format!("#N {}\nx = {}, y = {}, rule = {}\n",
+            self.name, width, height, rule);
+        let mut line = String::new();
+        for token in tokens {
+            if line.len() + token.len() > LINE_LENGTH {
+                text.push_str(&line);
+                text.push('\n');
+                line.clear();
+            }
+            line.push_str(&token);
+        }
+        text.push_str(&line);
+        text.push('\n');
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_SIDE: u32 = 1024;
+
+    #[test]
+    fn glider() {
+        let text = "#N Glider\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!\n";
+        let pattern = Pattern::parse(text, "unnamed", MAX_SIDE).unwrap();
+        assert_eq!(pattern.name, "Glider");
+        assert_eq!(pattern.dim, Dimensions::new(3, 3));
+        // The bottom row comes first.
+        assert_eq!(pattern.cells, vec![
+            1.0, 1.0, 1.0,
+            0.0, 0.0, 1.0,
+            0.0, 1.0, 0.0,
+        ]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let rule: Rule = "B36/S23".parse().unwrap();
+        let text = "x = 36, y = 9\n\
+            24bo11b$22bobo11b$12b2o6b2o12b2o$11bo3bo4b2o12b2o$\
+            2o8bo5bo3b2o14b$2o8bo3bob2o4bobo11b$10bo5bo7bo11b$\
+            11bo3bo20b$12b2o22b!";
+        let pattern = Pattern::parse(text, "Gosper glider gun", MAX_SIDE).unwrap();
+
+        let rle = pattern.to_rle(rule, 0.5);
+        assert!(rle.starts_with("#N Gosper glider gun\nx = 36, y = 9, rule = B36/S23\n"));
+        assert!(rle.lines().all(|line| line.len() <= LINE_LENGTH));
+
+        let again = Pattern::parse(&rle, "", MAX_SIDE).unwrap();
+        assert_eq!(again.name, pattern.name);
+        assert_eq!(again.dim, pattern.dim);
+        assert_eq!(again.cells, pattern.cells);
+    }
+
+    #[test]
+    fn empty_rows() {
+        let pattern = Pattern::parse("x = 2, y = 4\no2$bo!", "", MAX_SIDE).unwrap();
+        assert_eq!(pattern.cells, vec![
+            0.0, 0.0,
+            0.0, 1.0,
+            0.0, 0.0,
+            1.0, 0.0,
+        ]);
+        let rle = pattern.to_rle("B3/S23".parse().unwrap(), 0.5);
+        assert!(rle.ends_with("\no2$bo!\n"));
+    }
+
+    #[test]
+    fn bad_patterns() {
+        let parse = |text| Pattern::parse(text, "", MAX_SIDE);
+        assert!(parse("bob$2bo$3o!").is_err());
+        assert!(parse("x = 0, y = 3\n!").is_err());
+        assert!(parse("x = 3, y = 3\n4o!").is_err());
+        assert!(parse("x = 3, y = 3\no3$o!").is_err());
+        assert!(parse("x = 3, y = 3\n99999999999999999999999o!").is_err());
+        assert!(parse("x = 2000, y = 3\n!").is_err());
+    }
+}
@@ -0,0 +1,78 @@
+// Life-like cellular automaton rules, in "B3/S23" notation.
+
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rule {
+    birth: u32,     // bit N set: a dead cell with N live neighbors is born
+    survive: u32,   // bit N set: a live cell with N live neighbors survives
+}
+
+impl Default for Rule {
+    // Conway's Game of Life.
+    fn default() -> Self {
+        Rule {
+            birth: 1 << 3,
+            survive: (1 << 2) | (1 << 3),
+        }
+    }
+}
+
+impl Rule {
+    pub fn from_masks(
+        birth: u32,
+        survive: u32,
+    ) -> Self {
+        Rule {
+            birth: birth & 0x1ff,
+            survive: survive & 0x1ff,
+        }
+    }
+
+    pub fn birth(&self) -> u32 {
+        self.birth
+    }
+
+    pub fn survive(&self) -> u32 {
+        self.survive
+    }
+}
+
+// Parse the digits following a "B" or "S".
+fn parse_counts(
+    digits: &str,
+) -> Result<u32, String> {
+    digits.chars().try_fold(0, |mask, c| match c.to_digit(10) {
+        Some(n) if n <= 8 => Ok(mask | (1 << n)),
+        _ => Err(format!("bad neighbor count '{}'", c)),
+    })
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (b, s) = text.split_once('/')
+            .ok_or_else(|| format!("rule \"{}\" isn't of the form B3/S23", text))?;
+        let b = b.strip_prefix('B').or_else(|| b.strip_prefix('b'))
+            .ok_or_else(|| format!("rule part \"{}\" doesn't start with B", b))?;
+        let s = s.strip_prefix('S').or_else(|| s.strip_prefix('s'))
+            .ok_or_else(|| format!("rule part \"{}\" doesn't start with S", s))?;
+
+        Ok(Rule {
+            birth: parse_counts(b)?,
+            survive: parse_counts(s)?,
+        })
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counts = |mask: u32| (0..=8)
+            .filter(|n| mask & (1 << n) != 0)
+            .map(|n| n.to_string())
+            .collect::<String>();
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survive))
+    }
+}
@@ -0,0 +1,97 @@
+// Saving the rendered frame to a PNG file.
+
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{
+    capture::CaptureTarget,
+    dimensions::Dimensions,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use {
+    std::{fs::File, io::BufWriter},
+    pollster::block_on,
+};
+
+// Write RGBA pixel data out in PNG format.
+pub fn write_png(
+    out: impl Write,
+    dim: Dimensions,
+    rgba: &[u8],
+) -> io::Result<()> {
+    let mut encoder = png::Encoder::new(out, dim.width(), dim.height());
+    encoder.set_color(png::ColorType::RGBA);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    writer.write_image_data(rgba)
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+}
+
+pub struct Screenshot { }
+
+impl Screenshot {
+    // Render the current frame at "scale" times the window size, and save it.
+    // The render function draws what the window shows into a view of the
+    // given size, in the given format.
+    //
+    // This waits for the GPU to finish rendering, so it's not something
+    // that should be done every frame.
+    #[allow(clippy::too_many_arguments)]
+    pub fn save(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+        window_dim: Dimensions,
+        scale: u32,
+        path: &Path,
+        render: impl FnOnce(&mut wgpu::CommandEncoder, &wgpu::TextureView, Dimensions),
+    ) -> io::Result<()> {
+        // Don't ask for a texture bigger than the device can make.
+        let max = device.limits().max_texture_dimension_2d;
+        let largest = window_dim.width().max(window_dim.height()).max(1);
+        let scale = scale.min(max / largest).max(1);
+        let dim = Dimensions::new(
+            window_dim.width() * scale, window_dim.height() * scale);
+
+        let target = CaptureTarget::new(device, dim, format);
+        let buf = target.create_buffer(device, "screenshot buffer");
+
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("screenshot")
+            });
+        render(&mut command_encoder, target.view(), dim);
+        target.enqueue_copyout(&mut command_encoder, &buf);
+        queue.submit(Some(command_encoder.finish()));
+
+        // Start a request to map the buffer, and wait for it.
+        let buffer_slice = buf.slice(..);
+        let buffer_future = buffer_slice.map_async(wgpu::MapMode::Read);
+        device.poll(wgpu::Maintain::Wait);
+
+        // This doesn't work with wasm32.
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = (buffer_future, path);
+            Err(io::Error::new(io::ErrorKind::Other,
+                "screenshots don't work under wasm32"))
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            block_on(buffer_future)
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let rgba = target.to_rgba(&buffer_slice.get_mapped_range());
+            buf.unmap();
+
+            let mut file = BufWriter::new(File::create(path)?);
+            write_png(&mut file, dim, &rgba)?;
+            file.flush()?;
+            log::info!("saved {}x{} screenshot to {}",
+                dim.width(), dim.height(), path.display());
+            Ok(())
+        }
+    }
+}
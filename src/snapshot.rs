@@ -0,0 +1,181 @@
+// Full-fidelity snapshots of the simulation state.
+//
+// Unlike pattern formats, a snapshot keeps the exact floating-point value of
+// every cell and the state of the GPU's random number generators, so a run
+// that's restored from a snapshot carries on exactly as it would have.
+//
+// The file format is, with everything little-endian:
+//
+//   magic       8 bytes     "ZNDSSNAP"
+//   version     u32
+//   width       u32
+//   height      u32
+//   frame_num   u64
+//   birth       u32         rule masks; see rule.rs
+//   survive     u32
+//   threshold   f32
+//   cells       width * height f32's
+//   rand_state  width * height [u32; 4]'s
+//   ages        width * height [u32; 2]'s    (since version 2)
+//
+// Version 1 snapshots had no ages, so their cells start out as if they'd
+// never lived.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use crate::{
+    dimensions::Dimensions,
+    rule::Rule,
+};
+
+const MAGIC: &[u8; 8] = b"ZNDSSNAP";
+const VERSION: u32 = 2;
+
+// How many bytes come before the cells.
+const HEADER_BYTES: u64 = 40;
+
+pub struct Snapshot {
+    pub dim: Dimensions,
+    pub frame_num: usize,
+    pub rule: Rule,
+    pub threshold: f32,
+    pub cells: Vec<f32>,
+    pub rand_state: Vec<[u32; 4]>,
+    pub ages: Vec<[u32; 2]>,        // generations alive, generations since death
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut b = [0; 4];
+    input.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut b = [0; 8];
+    input.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+impl Snapshot {
+    pub fn write(
+        &self,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        assert_eq!(self.cells.len(), self.dim.area());
+        assert_eq!(self.rand_state.len(), self.dim.area());
+        assert_eq!(self.ages.len(), self.dim.area());
+
+        out.write_all(MAGIC)?;
+        out.write_all(&VERSION.to_le_bytes())?;
+        out.write_all(&self.dim.width().to_le_bytes())?;
+        out.write_all(&self.dim.height().to_le_bytes())?;
+        out.write_all(&(self.frame_num as u64).to_le_bytes())?;
+        out.write_all(&self.rule.birth().to_le_bytes())?;
+        out.write_all(&self.rule.survive().to_le_bytes())?;
+        out.write_all(&self.threshold.to_le_bytes())?;
+        for c in &self.cells {
+            out.write_all(&c.to_le_bytes())?;
+        }
+        for r in self.rand_state.iter().flatten() {
+            out.write_all(&r.to_le_bytes())?;
+        }
+        for a in self.ages.iter().flatten() {
+            out.write_all(&a.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Read a snapshot that's len bytes long, for a grid that can't be more
+    // than max_side cells across or down.  The size is checked against
+    // both of those before anything that size is made.
+    pub fn read(
+        input: &mut impl Read,
+        len: u64,
+        max_side: u32,
+    ) -> io::Result<Self> {
+        let mut magic = [0; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a zounds snapshot".to_string()));
+        }
+        let version = read_u32(input)?;
+        if version == 0 || version > VERSION {
+            return Err(invalid(format!(
+                "snapshot is version {}, but only 1 to {} can be read", version, VERSION)));
+        }
+
+        let dim = Dimensions::new(read_u32(input)?, read_u32(input)?);
+        if dim.width() == 0 || dim.height() == 0 {
+            return Err(invalid(format!("snapshot is {}x{}, which is empty",
+                dim.width(), dim.height())));
+        }
+        if dim.width() > max_side || dim.height() > max_side {
+            return Err(invalid(format!(
+                "snapshot is {}x{}, but the grid can't be more than {}x{}",
+                dim.width(), dim.height(), max_side, max_side)));
+        }
+        // Each cell has a value, random state, and since version 2, ages.
+        let cell_bytes: u64 = if version >= 2 { 4 + 16 + 8 } else { 4 + 16 };
+        let needed = HEADER_BYTES + dim.area() as u64 * cell_bytes;
+        if needed > len {
+            return Err(invalid(format!(
+                "snapshot is {} bytes, but a {}x{} one would be {}",
+                len, dim.width(), dim.height(), needed)));
+        }
+
+        let frame_num = read_u64(input)? as usize;
+        let rule = Rule::from_masks(read_u32(input)?, read_u32(input)?);
+        let threshold = f32::from_bits(read_u32(input)?);
+
+        let cells = (0..dim.area())
+            .map(|_| Ok(f32::from_bits(read_u32(input)?)))
+            .collect::<io::Result<Vec<f32>>>()?;
+        let rand_state = (0..dim.area())
+            .map(|_| Ok([
+                read_u32(input)?, read_u32(input)?,
+                read_u32(input)?, read_u32(input)?,
+            ]))
+            .collect::<io::Result<Vec<[u32; 4]>>>()?;
+        let ages = if version >= 2 {
+            (0..dim.area())
+                .map(|_| Ok([read_u32(input)?, read_u32(input)?]))
+                .collect::<io::Result<Vec<[u32; 2]>>>()?
+        } else {
+            vec![[0; 2]; dim.area()]
+        };
+
+        Ok(Snapshot {
+            dim,
+            frame_num,
+            rule,
+            threshold,
+            cells,
+            rand_state,
+            ages,
+        })
+    }
+
+    pub fn save(
+        &self,
+        path: &Path,
+    ) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+
+    pub fn load(
+        path: &Path,
+        max_side: u32,
+    ) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Snapshot::read(&mut BufReader::new(file), len, max_side)
+    }
+}
@@ -0,0 +1,375 @@
+// Watching a simulation in a terminal, such as over SSH.
+//
+// There's no window: the simulation runs on whatever GPU there is, and after
+// each step a compute pass shrinks the grid down to one value per dot that
+// the terminal can show.  That's read back and drawn with 24-bit ANSI
+// colors, either as the halves of "▀" characters, with the top half in the
+// foreground color and the bottom half in the background, or as the 2x4
+// dots of braille characters, colored by the brightest of their dots.
+// Cells are colored the same way as in renderer.wgsl.
+
+use std::borrow::Cow;
+use std::io::{self, BufWriter, Stdout, Write};
+use std::time::{Duration, Instant};
+
+use bytemuck::{Pod, Zeroable};
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+    execute, queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use pollster::block_on;
+
+use crate::{
+    bindable::{Bindable, BindAccess, Binder, Buffer, Buffer2D, BufferType, Texture},
+    colormap::{Colormap, Colormaps},
+    config::Config,
+    dimensions::Dimensions,
+    headless,
+    options::Options,
+    window::{self, FrameRate},
+    LifeProg,
+};
+
+// This must match the value of the workgroup_size() annotation in terminal.wgsl
+const WORKGROUP_SIZE: (u32, u32) = (8, 8);
+
+// Terminals don't say how fast they can keep up, so this is the frame rate
+// unless one is given.
+const DEFAULT_FPS: f64 = 30.0;
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the compute pipeline in WGSL.
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct ShrinkParams {
+    width: u32,         // in dots
+    height: u32,
+    block: u32,         // how many cells across and down go into a dot
+    _pad: u32,
+}
+
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Style {
+    HalfBlock,
+    Braille,
+}
+
+impl Style {
+    // How many dots there are across and down each character.
+    fn dots_per_char(self) -> (u16, u16) {
+        match self {
+            Style::HalfBlock => (1, 2),
+            Style::Braille => (2, 4),
+        }
+    }
+}
+
+// The bits in a braille character for each of its dots, from the top left,
+// going across and then down.
+const BRAILLE_BITS: [[u32; 2]; 4] = [
+    [0x01, 0x08],
+    [0x02, 0x10],
+    [0x04, 0x20],
+    [0x40, 0x80],
+];
+
+// Shrinks the grid down to the dots that are shown, and reads them back.
+// The parameters are kept alive by the bind group that uses them.
+struct Shrink {
+    dots: Buffer2D<f32>,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    block: u32,
+}
+
+impl Shrink {
+    // Fit a grid into the given number of dots, keeping the cells square.
+    fn new(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        grid: Dimensions,
+        fit: Dimensions,
+        life_params: &dyn Bindable,
+        cells: &dyn Bindable,
+    ) -> Self {
+        let block = ((grid.width() + fit.width() - 1) / fit.width())
+            .max((grid.height() + fit.height() - 1) / fit.height())
+            .max(1);
+        let dim = Dimensions::new((grid.width() + block - 1) / block,
+            (grid.height() + block - 1) / block);
+
+        let params = Buffer::new_init(device, "terminal shrink parameters",
+            BufferType::Uniform, bytemuck::bytes_of(&ShrinkParams {
+                width: dim.width(),
+                height: dim.height(),
+                block,
+                _pad: 0,
+            }));
+        let dots = Buffer2D::new(device, "terminal dots", dim);
+        let args: Vec<(_, &dyn Bindable)> = vec![
+            (BindAccess::ReadOnly,    life_params),
+            (BindAccess::ReadOnly,    &params),
+            (BindAccess::ReadSampled, cells),
+            (BindAccess::WriteOnly,   &dots),
+        ];
+        let (pipeline, bind_group) = Binder::bind_up(device, shader, "main", &args);
+
+        Shrink {
+            dots,
+            pipeline,
+            bind_group,
+            block,
+        }
+    }
+
+    fn dim(&self) -> Dimensions {
+        self.dots.dim()
+    }
+
+    // Shrink the grid as it is after the commands that have been encoded,
+    // and wait to read back the dots.
+    fn read(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        mut command_encoder: wgpu::CommandEncoder,
+    ) -> Vec<f32> {
+        let dim = self.dim();
+        let xgroups = (dim.width() + WORKGROUP_SIZE.0 - 1) / WORKGROUP_SIZE.0;
+        let ygroups = (dim.height() + WORKGROUP_SIZE.1 - 1) / WORKGROUP_SIZE.1;
+        {
+            let mut cpass = command_encoder.begin_compute_pass(
+                &wgpu::ComputePassDescriptor {
+                    label: Some("terminal shrink")
+                });
+            cpass.set_pipeline(&self.pipeline);
+            cpass.set_bind_group(0, &self.bind_group, &[]);
+            cpass.dispatch(xgroups, ygroups, 1);
+        }
+        queue.submit(Some(command_encoder.finish()));
+        self.dots.copyout_vec(device, queue)
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+// Puts the terminal back the way it was, however the run ends.
+struct Screen {
+    out: BufWriter<Stdout>,
+}
+
+impl Screen {
+    fn new() -> crossterm::Result<Self> {
+        let mut out = BufWriter::new(io::stdout());
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, Hide, Clear(ClearType::All))?;
+        Ok(Screen {
+            out,
+        })
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, ResetColor, Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+// How many dots the terminal can show, leaving a line for the status.
+fn terminal_dots(
+    style: Style,
+) -> crossterm::Result<Dimensions> {
+    let (cols, rows) = terminal::size()?;
+    let (across, down) = style.dots_per_char();
+    Ok(Dimensions::new((cols.max(1) * across) as u32,
+        (rows.saturating_sub(1).max(1) * down) as u32))
+}
+
+// The color of a dot, as in renderer.wgsl; dead cells are black.
+fn dot_color(
+    value: f32,
+    threshold: f32,
+    colormap: &Colormap,
+) -> Option<[u8; 3]> {
+    if value <= threshold {
+        return None;
+    }
+    Some(colormap.color((value - threshold) / (1.0 - threshold)))
+}
+
+fn rgb(
+    color: Option<[u8; 3]>,
+) -> Color {
+    let [r, g, b] = color.unwrap_or([0, 0, 0]);
+    Color::Rgb { r, g, b }
+}
+
+// Draw the dots, which have the bottom row of the grid first, so that the
+// grid is the same way up as in the window.
+fn draw(
+    out: &mut impl Write,
+    style: Style,
+    dots: &[f32],
+    dim: Dimensions,
+    threshold: f32,
+    colormap: &Colormap,
+) -> crossterm::Result<()> {
+    let (w, h) = (dim.width() as usize, dim.height() as usize);
+    let (across, down) = style.dots_per_char();
+    let (across, down) = (across as usize, down as usize);
+    let dot = |x: usize, y: usize| -> Option<f32> {
+        if x < w && y < h {
+            Some(dots[(h - 1 - y) * w + x])
+        } else {
+            None
+        }
+    };
+
+    let (mut fg, mut bg) = (None, None);
+    for row in 0..((h + down - 1) / down) {
+        queue!(out, MoveTo(0, row as u16))?;
+        for col in 0..((w + across - 1) / across) {
+            let (x, y) = (col * across, row * down);
+            let (ch, top, bottom) = match style {
+                Style::HalfBlock => {
+                    let color = |y| dot(x, y)
+                        .and_then(|v| dot_color(v, threshold, colormap));
+                    ('▀', color(y), color(y + 1))
+                },
+                Style::Braille => {
+                    let mut bits = 0;
+                    let mut brightest: Option<f32> = None;
+                    for (j, row_bits) in BRAILLE_BITS.iter().enumerate() {
+                        for (i, bit) in row_bits.iter().enumerate() {
+                            if let Some(v) = dot(x + i, y + j) {
+                                if v > threshold {
+                                    bits |= *bit;
+                                    brightest = Some(brightest.map_or(v, |b| b.max(v)));
+                                }
+                            }
+                        }
+                    }
+                    let ch = std::char::from_u32(0x2800 + bits).unwrap_or(' ');
+                    let color = brightest
+                        .and_then(|v| dot_color(v, threshold, colormap));
+                    (ch, color, None)
+                },
+            };
+            // Only send the colors that change.
+            if fg != Some(top) {
+                queue!(out, SetForegroundColor(rgb(top)))?;
+                fg = Some(top);
+            }
+            if bg != Some(bottom) {
+                queue!(out, SetBackgroundColor(rgb(bottom)))?;
+                bg = Some(bottom);
+            }
+            queue!(out, Print(ch))?;
+        }
+    }
+    queue!(out, ResetColor)
+}
+
+// ---------------------------------------------------------------------------
+
+// Run the simulation in the terminal until it's quit.
+pub fn run(
+    options: &Options,
+) -> crossterm::Result<()> {
+    // (No logging, since it would scribble over the picture.)
+    let backend = wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+    let instance = wgpu::Instance::new(backend);
+    let adapter = block_on(headless::request_adapter(&instance, backend));
+    let (device, queue) = block_on(window::request_device::<LifeProg>(&adapter));
+
+    let style = if options.braille { Style::Braille } else { Style::HalfBlock };
+    let mut colormaps = Colormaps::new();
+    if let Some(name) = &options.colormap {
+        colormaps.choose(name).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    }
+    let colormap = colormaps.current();
+
+    // The grid fills the terminal, one cell to a dot, unless its size
+    // has been given.
+    let fit = terminal_dots(style)?;
+    let dim = options.grid.unwrap_or(fit);
+    let (rule, threshold) = (options.rule, options.threshold);
+    let params = LifeProg::params(&device, dim, rule, threshold);
+    let texture = Texture::new(&device, dim, wgpu::TextureFormat::R32Float);
+    let mut life = LifeProg::initial_life(&device, &queue, dim, &params, &texture);
+
+    let shader = device.create_shader_module(
+        &wgpu::ShaderModuleDescriptor {
+            label: Some("terminal"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                include_str!("terminal.wgsl"))),
+        });
+    let mut shrink = Shrink::new(&device, &shader, dim, fit, &params, &texture);
+
+    // The command line wins over the config file, as with a window.
+    let settings = Config::load(options.config_path.as_deref());
+    let frame_rate = options.frame_rate.or(settings.frame_rate);
+    let frame_time = match frame_rate.unwrap_or(FrameRate::Fps(DEFAULT_FPS)) {
+        FrameRate::Fps(fps) => Duration::from_secs_f64(1.0 / fps),
+        FrameRate::Monitor => Duration::from_secs_f64(1.0 / DEFAULT_FPS),
+        FrameRate::Uncapped => Duration::from_secs(0),
+    };
+
+    let mut screen = Screen::new()?;
+    let mut paused = false;
+    loop {
+        let frame_start = Instant::now();
+
+        // Space pauses, "s" steps once while paused, and "q" quits.
+        let mut step = !paused;
+        let mut timeout = frame_time;
+        while event::poll(timeout)? {
+            match event::read()? {
+                Event::Key(KeyEvent { code, modifiers }) => match code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) =>
+                        return Ok(()),
+                    KeyCode::Char(' ') | KeyCode::Char('p') => paused = !paused,
+                    KeyCode::Char('s') | KeyCode::Char('.') | KeyCode::Right =>
+                        step = true,
+                    _ => {},
+                },
+                Event::Resize(_, _) => {
+                    shrink = Shrink::new(&device, &shader, dim,
+                        terminal_dots(style)?, &params, &texture);
+                    queue!(screen.out, ResetColor, Clear(ClearType::All))?;
+                },
+                _ => {},
+            }
+            timeout = frame_time.saturating_sub(frame_start.elapsed());
+        }
+
+        let mut command_encoder =
+            device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("terminal")
+            });
+        if step {
+            life.step(&mut command_encoder);
+        }
+        let dots = shrink.read(&device, &queue, command_encoder);
+
+        draw(&mut screen.out, style, &dots, shrink.dim(), threshold, colormap)?;
+        let status = format!(
+            "generation {}  {}x{} ({} per dot)  rule {}  threshold {:.2}{}  \
+             [space] pause  [s] step  [q] quit",
+            life.frame_num(), dim.width(), dim.height(), shrink.block,
+            rule, threshold, if paused { "  PAUSED" } else { "" });
+        let (cols, rows) = terminal::size()?;
+        let status: String = status.chars().take(cols as usize).collect();
+        queue!(screen.out, MoveTo(0, rows.saturating_sub(1)),
+            Clear(ClearType::CurrentLine), Print(status))?;
+        screen.out.flush()?;
+    }
+}
@@ -0,0 +1,53 @@
+// Shrinks the grid down to one value per dot that the terminal can show,
+// for terminal.rs.  Each dot gets the largest value in its block of cells,
+// so that lone live cells don't disappear.
+
+[[block]]
+struct LifeParams {
+    width : u32;
+    height : u32;
+    threshold : f32;
+    birth : u32;
+    survive : u32;
+};
+
+[[block]]
+struct Shrink {
+    width : u32;        // in dots
+    height : u32;
+    block : u32;        // how many cells across and down go into a dot
+    pad0 : u32;
+};
+
+[[block]]
+struct Dots {
+    dots : array<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: LifeParams;
+[[group(0), binding(1)]] var<uniform> shrink: Shrink;
+[[group(0), binding(2)]] var cells: texture_2d<f32>;
+[[group(0), binding(3)]] var<storage, read_write> dots: Dots;
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    let X : u32 = global_id.x;
+    let Y : u32 = global_id.y;
+    if (X >= shrink.width || Y >= shrink.height) {
+        return;
+    }
+
+    var value : f32 = 0.0;
+    for (var j: u32 = 0u32; j < shrink.block; j = j + 1u32) {
+        for (var i: u32 = 0u32; i < shrink.block; i = i + 1u32) {
+            let x : u32 = X * shrink.block + i;
+            let y : u32 = Y * shrink.block + j;
+            if (x < params.width && y < params.height) {
+                let coord : vec2<i32> = vec2<i32>(i32(x), i32(y));
+                value = max(value, textureLoad(cells, coord, 0).x);
+            }
+        }
+    }
+
+    dots.dots[Y * shrink.width + X] = value;
+}
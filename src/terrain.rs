@@ -0,0 +1,432 @@
+// A 3D view of the grid, as a landscape.
+//
+// Instead of the flat quad that the Renderer draws, a mesh covering the grid
+// is pushed up by the value of the cell under each vertex, lit from one
+// side, and looked at by a camera that orbits around the middle of the grid.
+// Big grids get a coarser mesh than one vertex per cell, so that it doesn't
+// get too big; each vertex still reads exactly one cell.
+
+use bytemuck::{Pod, Zeroable};
+use cgmath::{Deg, InnerSpace, Matrix4, Point3, Vector3};
+use std::{borrow::Cow, mem};
+use wgpu::util::DeviceExt;
+
+use crate::{
+    bindable::{Bindable, BindAccess, Buffer, BufferType},
+    colormap::{Colormap, PaletteTexture},
+    dimensions::Dimensions,
+};
+
+// The mesh has at most this many vertices across and down.
+const MAX_MESH: u32 = 512;
+
+// How high a fully alive cell is, as a fraction of the grid's longer side.
+const HEIGHT: f32 = 0.08;
+
+const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float;
+
+// cgmath's projections go from -1 to 1 in z, but wgpu's go from 0 to 1.
+#[rustfmt::skip]
+const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
+    1.0, 0.0, 0.0, 0.0,
+    0.0, 1.0, 0.0, 0.0,
+    0.0, 0.0, 0.5, 0.0,
+    0.0, 0.0, 0.5, 1.0,
+);
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the shaders in WGSL.
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct TerrainUniform {
+    view_proj: [[f32; 4]; 4],
+    light: [f32; 4],        // the direction towards the light
+    size: [f32; 2],         // of the grid, in world units
+    mesh: [f32; 2],         // how many vertices there are across and down
+    height: f32,            // of a fully alive cell, in world units
+    _pad: [f32; 3],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct Vertex {
+    _tex_coord: [f32; 2],
+}
+
+// ---------------------------------------------------------------------------
+
+// Where the camera is, looking at the middle of the grid.
+struct Orbit {
+    yaw: f32,           // in degrees, around the vertical
+    pitch: f32,         // in degrees above the grid
+    distance: f32,      // in world units; the grid's longer side is 1
+}
+
+impl Orbit {
+    const MIN_PITCH: f32 = 5.0;
+    const MAX_PITCH: f32 = 89.0;
+    const MIN_DISTANCE: f32 = 0.1;
+    const MAX_DISTANCE: f32 = 5.0;
+
+    // How many degrees the camera turns for each pixel the mouse moves.
+    const DEGREES_PER_PIXEL: f32 = 0.3;
+
+    fn new() -> Self {
+        Orbit {
+            yaw: -30.0,
+            pitch: 40.0,
+            distance: 1.5,
+        }
+    }
+
+    fn eye(&self) -> Point3<f32> {
+        let (yaw, pitch) = (self.yaw.to_radians(), self.pitch.to_radians());
+        Point3::new(
+            self.distance * pitch.cos() * yaw.sin(),
+            self.distance * pitch.sin(),
+            self.distance * pitch.cos() * yaw.cos(),
+        )
+    }
+
+    fn view_proj(
+        &self,
+        aspect: f32,
+    ) -> Matrix4<f32> {
+        let view = Matrix4::look_at_rh(self.eye(), Point3::new(0.0, 0.0, 0.0),
+            Vector3::unit_y());
+        let proj = cgmath::perspective(Deg(45.0), aspect, 0.01, 20.0);
+        OPENGL_TO_WGPU_MATRIX * proj * view
+    }
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct Terrain {
+    uniform: Buffer,
+    palette: PaletteTexture,
+    layout: wgpu::BindGroupLayout,
+    bind_group: wgpu::BindGroup,
+    pipeline: wgpu::RenderPipeline,
+    vertex_buf: wgpu::Buffer,
+    index_buf: wgpu::Buffer,
+    index_count: usize,
+    depth: wgpu::TextureView,
+    window: Dimensions,
+    grid: Dimensions,
+    mesh: Dimensions,
+    orbit: Orbit,
+}
+
+impl Terrain {
+    // The mesh for a grid: texture coordinates at the middle of the cells
+    // that the vertices read, and two triangles for each square between them.
+    fn create_mesh(
+        grid: Dimensions,
+    ) -> (Dimensions, Vec<Vertex>, Vec<u32>) {
+        let mesh = Dimensions::new(grid.width().min(MAX_MESH).max(2),
+            grid.height().min(MAX_MESH).max(2));
+        let (nx, ny) = (mesh.width(), mesh.height());
+
+        let mut vertex_data = Vec::with_capacity(mesh.area());
+        for j in 0..ny {
+            for i in 0..nx {
+                vertex_data.push(Vertex {
+                    _tex_coord: [(i as f32 + 0.5) / nx as f32,
+                        (j as f32 + 0.5) / ny as f32],
+                });
+            }
+        }
+
+        let mut index_data = Vec::with_capacity(((nx - 1) * (ny - 1) * 6) as usize);
+        for j in 0..(ny - 1) {
+            for i in 0..(nx - 1) {
+                let a = j * nx + i;
+                let b = a + nx;
+                index_data.extend_from_slice(&[a, a + 1, b + 1, b + 1, b, a]);
+            }
+        }
+
+        (mesh, vertex_data, index_data)
+    }
+
+    fn create_depth(
+        device: &wgpu::Device,
+        window: Dimensions,
+    ) -> wgpu::TextureView {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("terrain depth"),
+            size: wgpu::Extent3d {
+                width: window.width(),
+                height: window.height(),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        });
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    // The arguments are params, texture, the terrain uniform and the palette.
+    fn bind(
+        device: &wgpu::Device,
+        layout: &wgpu::BindGroupLayout,
+        args: [&dyn Bindable; 4],
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &args.iter().enumerate().map(|(idx, arg)| {
+                wgpu::BindGroupEntry {
+                    binding: idx as _,
+                    resource: arg.binding_resource(),
+                }}).collect::<Vec<_>>(),
+            label: Some("terrain"),
+        })
+    }
+
+    pub fn new(
+        format: wgpu::TextureFormat,
+        device: &wgpu::Device,
+        window: Dimensions,
+        grid: Dimensions,
+        params: &dyn Bindable,
+        texture: &dyn Bindable,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("terrain"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    include_str!("terrain.wgsl"))),
+            });
+
+        // The uniform is filled in before each frame, and the colormap
+        // by set_colormap().
+        let uniform = Buffer::new_init(device, "terrain parameters",
+            BufferType::Uniform, bytemuck::bytes_of(&TerrainUniform::zeroed()));
+        let palette = PaletteTexture::new(device);
+
+        let args: [&dyn Bindable; 4] = [params, texture, &uniform, &palette];
+        let layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                entries: &args.iter().enumerate().map(|(idx, arg)| {
+                    wgpu::BindGroupLayoutEntry {
+                        binding: idx as _,
+                        visibility: wgpu::ShaderStages::VERTEX
+                                  | wgpu::ShaderStages::FRAGMENT,
+                        ty: arg.binding_type(BindAccess::ReadSampled),
+                        count: None,
+                    }}).collect::<Vec<_>>(),
+                label: Some("terrain"),
+            });
+        let bind_group = Terrain::bind(device, &layout, args);
+
+        let vertex_buffers = [wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Vertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    format: wgpu::VertexFormat::Float32x2,
+                    offset: 0,
+                    shader_location: 0,
+                },
+            ],
+        }];
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("terrain"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+        // The mesh can be seen from underneath, so nothing is culled.
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("terrain"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &vertex_buffers,
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[format.into()],
+                }),
+                primitive: wgpu::PrimitiveState::default(),
+                depth_stencil: Some(wgpu::DepthStencilState {
+                    format: DEPTH_FORMAT,
+                    depth_write_enabled: true,
+                    depth_compare: wgpu::CompareFunction::Less,
+                    stencil: wgpu::StencilState::default(),
+                    bias: wgpu::DepthBiasState::default(),
+                }),
+                multisample: wgpu::MultisampleState::default(),
+            });
+
+        let (mesh, vertex_buf, index_buf, index_count) =
+            Terrain::create_buffers(device, grid);
+
+        Terrain {
+            uniform,
+            palette,
+            layout,
+            bind_group,
+            pipeline,
+            vertex_buf,
+            index_buf,
+            index_count,
+            depth: Terrain::create_depth(device, window),
+            window,
+            grid,
+            mesh,
+            orbit: Orbit::new(),
+        }
+    }
+
+    fn create_buffers(
+        device: &wgpu::Device,
+        grid: Dimensions,
+    ) -> (Dimensions, wgpu::Buffer, wgpu::Buffer, usize) {
+        let (mesh, vertex_data, index_data) = Terrain::create_mesh(grid);
+        let vertex_buf = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("terrain vertices"),
+                contents: bytemuck::cast_slice(&vertex_data),
+                usage: wgpu::BufferUsages::VERTEX,
+            });
+        let index_buf = device.create_buffer_init(
+            &wgpu::util::BufferInitDescriptor {
+                label: Some("terrain indices"),
+                contents: bytemuck::cast_slice(&index_data),
+                usage: wgpu::BufferUsages::INDEX,
+            });
+        (mesh, vertex_buf, index_buf, index_data.len())
+    }
+
+    // The window has changed size.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        window: Dimensions,
+    ) {
+        self.depth = Terrain::create_depth(device, window);
+        self.window = window;
+    }
+
+    // Show a different grid, which might be a different size.
+    pub fn rebind(
+        &mut self,
+        device: &wgpu::Device,
+        grid: Dimensions,
+        params: &dyn Bindable,
+        texture: &dyn Bindable,
+    ) {
+        self.bind_group = Terrain::bind(device, &self.layout,
+            [params, texture, &self.uniform, &self.palette]);
+        if grid != self.grid {
+            let (mesh, vertex_buf, index_buf, index_count) =
+                Terrain::create_buffers(device, grid);
+            self.mesh = mesh;
+            self.vertex_buf = vertex_buf;
+            self.index_buf = index_buf;
+            self.index_count = index_count;
+            self.grid = grid;
+        }
+    }
+
+    // Turn the camera around the grid, as the mouse is dragged.
+    pub fn rotate(
+        &mut self,
+        (dx, dy): (f64, f64),
+    ) {
+        let orbit = &mut self.orbit;
+        orbit.yaw -= dx as f32 * Orbit::DEGREES_PER_PIXEL;
+        orbit.pitch = (orbit.pitch + dy as f32 * Orbit::DEGREES_PER_PIXEL)
+            .clamp(Orbit::MIN_PITCH, Orbit::MAX_PITCH);
+    }
+
+    // Move the camera closer by a factor.
+    pub fn zoom(
+        &mut self,
+        factor: f32,
+    ) {
+        let orbit = &mut self.orbit;
+        orbit.distance = (orbit.distance / factor)
+            .clamp(Orbit::MIN_DISTANCE, Orbit::MAX_DISTANCE);
+    }
+
+    pub fn reset_view(&mut self) {
+        self.orbit = Orbit::new();
+    }
+
+    // Change how live cells are colored.
+    pub fn set_colormap(
+        &self,
+        queue: &wgpu::Queue,
+        colormap: &Colormap,
+    ) {
+        self.palette.set(queue, colormap);
+    }
+
+    // Draw into a view of the given size, which is usually the window's.
+    pub fn render(
+        &self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        dim: Dimensions,
+    ) {
+        // Anything else, like a screenshot, needs a depth buffer of its own.
+        let other_depth;
+        let depth = if dim == self.window {
+            &self.depth
+        } else {
+            other_depth = Terrain::create_depth(device, dim);
+            &other_depth
+        };
+
+        // The longer side of the grid is 1 across.
+        let (w, h) = (self.grid.width() as f32, self.grid.height() as f32);
+        let longer = w.max(h);
+        let aspect = dim.width() as f32 / dim.height().max(1) as f32;
+        let light = Vector3::new(-0.4f32, 1.0, 0.6).normalize();
+        let uniform = TerrainUniform {
+            view_proj: self.orbit.view_proj(aspect).into(),
+            light: [light.x, light.y, light.z, 0.0],
+            size: [w / longer, h / longer],
+            mesh: [self.mesh.width() as f32, self.mesh.height() as f32],
+            height: HEIGHT,
+            _pad: [0.0; 3],
+        };
+        queue.write_buffer(self.uniform.buf(), 0, bytemuck::bytes_of(&uniform));
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("terrain"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: depth,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: false,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, &self.bind_group, &[]);
+        rpass.set_index_buffer(self.index_buf.slice(..), wgpu::IndexFormat::Uint32);
+        rpass.set_vertex_buffer(0, self.vertex_buf.slice(..));
+        rpass.draw_indexed(0..self.index_count as u32, 0, 0..1);
+    }
+}
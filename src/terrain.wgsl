@@ -0,0 +1,101 @@
+// Draws the grid as a landscape, with each vertex of a mesh pushed up by the
+// value of the cell under it.  This goes with terrain.rs.
+
+[[block]]
+struct LifeParams {
+    width: u32;
+    height: u32;
+    threshold: f32;
+    birth: u32;
+    survive: u32;
+};
+
+[[block]]
+struct Terrain {
+    view_proj: mat4x4<f32>;
+    light: vec4<f32>;       // the direction towards the light
+    size: vec2<f32>;        // of the grid, in world units
+    mesh: vec2<f32>;        // how many vertices there are across and down
+    height: f32;            // of a fully alive cell, in world units
+    pad0: f32;
+    pad1: f32;
+    pad2: f32;
+};
+
+[[group(0), binding(0)]] var<uniform> params: LifeParams;
+[[group(0), binding(1)]] var cells: texture_2d<f32>;
+[[group(0), binding(2)]] var<uniform> terrain: Terrain;
+
+// The colormap, from barely alive to fully alive.
+[[group(0), binding(3)]] var palette: texture_1d<f32>;
+
+// The value of the cell at a texture coordinate, which wraps around.
+fn value_at(tex_coord: vec2<f32>) -> f32 {
+    let W: i32 = i32(params.width);
+    let H: i32 = i32(params.height);
+    let x: i32 = i32(floor(tex_coord.x * f32(W)));
+    let y: i32 = i32(floor(tex_coord.y * f32(H)));
+    let coord: vec2<i32> = vec2<i32>((x % W + W) % W, (y % H + H) % H);
+    return clamp(textureLoad(cells, coord, 0).x, 0.0, 1.0);
+}
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] normal: vec3<f32>;
+    [[location(1)]] value: f32;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+    [[location(0)]] tex_coord: vec2<f32>,
+) -> VertexOutput {
+    let value: f32 = value_at(tex_coord);
+
+    // The grid lies flat, with its bottom edge towards +z.
+    let pos: vec3<f32> = vec3<f32>(
+        (tex_coord.x - 0.5) * terrain.size.x,
+        value * terrain.height,
+        (0.5 - tex_coord.y) * terrain.size.y);
+
+    // The slope comes from the neighboring vertices.
+    let du: f32 = 1.0 / terrain.mesh.x;
+    let dv: f32 = 1.0 / terrain.mesh.y;
+    let dx: f32 = (value_at(tex_coord + vec2<f32>(du, 0.0))
+                 - value_at(tex_coord - vec2<f32>(du, 0.0)))
+                * terrain.height / (2.0 * du * terrain.size.x);
+    let dz: f32 = (value_at(tex_coord - vec2<f32>(0.0, dv))
+                 - value_at(tex_coord + vec2<f32>(0.0, dv)))
+                * terrain.height / (2.0 * dv * terrain.size.y);
+
+    var out: VertexOutput;
+    out.position = terrain.view_proj * vec4<f32>(pos, 1.0);
+    out.normal = vec3<f32>(-dx, 1.0, -dz);
+    out.value = value;
+    return out;
+}
+
+// The color a fraction a of the way along the colormap.
+fn palette_color(a: f32) -> vec3<f32> {
+    let size: i32 = textureDimensions(palette);
+    let i: i32 = i32(a * f32(size - 1) + 0.5);
+    let color: vec4<f32> = textureLoad(palette, i, 0);
+    return color.rgb;
+}
+
+[[stage(fragment)]]
+fn fs_main(
+    in: VertexOutput,
+) -> [[location(0)]] vec4<f32> {
+    let thresh: f32 = params.threshold;
+
+    // Dead cells are the ground, and live ones come from the colormap.
+    var color: vec3<f32> = vec3<f32>(0.1, 0.1, 0.12);
+    if (in.value > thresh) {
+        color = palette_color(min((in.value - thresh) / (1.0 - thresh), 1.0));
+    }
+
+    // Some light gets everywhere, and the rest comes from one direction.
+    let n: vec3<f32> = normalize(in.normal);
+    let lambert: f32 = max(dot(n, terrain.light.xyz), 0.0);
+    return vec4<f32>(color * (0.3 + 0.7 * lambert), 1.0);
+}
@@ -0,0 +1,382 @@
+// Drawing lines of text over the frame, for things like the help screen.
+//
+// The glyphs of the bitmap font in font.rs are uploaded once, side by side
+// in an atlas.  The text itself is a small texture with a character code
+// for each cell, which is rewritten whenever the text changes, and the
+// shader looks up each pixel's glyph in the atlas as it draws the text as
+// a single blended quad.
+
+use bytemuck::{Pod, Zeroable};
+use std::{borrow::Cow, num::NonZeroU32};
+
+use crate::{
+    bindable::{Buffer, BufferType},
+    dimensions::Dimensions,
+    font::{self, GLYPH_HEIGHT, GLYPH_WIDTH},
+};
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the shaders in WGSL.
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct TextParams {
+    origin: [f32; 2],
+    size: [f32; 2],
+    window: [f32; 2],
+    text: [f32; 2],         // the size before scaling, with the margins
+    foreground: [f32; 4],
+    background: [f32; 4],
+    margin: f32,
+    _pad: [f32; 3],
+}
+
+// ---------------------------------------------------------------------------
+
+// Space between the text and the edge of its background, in pixels.
+const MARGIN: usize = 4;
+
+const FOREGROUND: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND: [f32; 4] = [0.0, 0.0, 0.0, 0.69];
+
+// The atlas has a glyph for each ASCII code; anything else is drawn as a
+// question mark.
+const ATLAS_GLYPHS: usize = 128;
+
+fn texture_size(
+    dim: Dimensions,
+) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: dim.width(),
+        height: dim.height(),
+        depth_or_array_layers: 1,
+    }
+}
+
+fn write_texture(
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    dim: Dimensions,
+    data: &[u8],
+) {
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        data,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: NonZeroU32::new(dim.width()),
+            rows_per_image: None,
+        },
+        texture_size(dim),
+    );
+}
+
+// The character codes of the text, one byte per cell.
+struct Cells {
+    texture: wgpu::Texture,
+    dim: Dimensions,        // which might have room for more text
+}
+
+pub struct TextOverlay {
+    lines: Vec<String>,
+    dirty: bool,
+    params: Buffer,
+    layout: wgpu::BindGroupLayout,
+    pipeline: wgpu::RenderPipeline,
+    atlas: wgpu::TextureView,
+    cells: Option<Cells>,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl TextOverlay {
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("text overlay"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    include_str!("text.wgsl"))),
+            });
+
+        let params = Buffer::new(device, "text parameters",
+            BufferType::Uniform, std::mem::size_of::<TextParams>());
+
+        let layout = device.create_bind_group_layout(
+            &wgpu::BindGroupLayoutDescriptor {
+                label: Some("text bind group layout"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Uint,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float {
+                                filterable: false,
+                            },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        let pipeline_layout = device.create_pipeline_layout(
+            &wgpu::PipelineLayoutDescriptor {
+                label: Some("text pipeline layout"),
+                bind_group_layouts: &[&layout],
+                push_constant_ranges: &[],
+            });
+        let pipeline = device.create_render_pipeline(
+            &wgpu::RenderPipelineDescriptor {
+                label: Some("text pipeline"),
+                layout: Some(&pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: "vs_main",
+                    buffers: &[],
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: "fs_main",
+                    targets: &[wgpu::ColorTargetState {
+                        format,
+                        blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    }],
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleStrip,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+            });
+
+        TextOverlay {
+            lines: Vec::new(),
+            dirty: false,
+            params,
+            layout,
+            pipeline,
+            atlas: TextOverlay::create_atlas(device, queue),
+            cells: None,
+            bind_group: None,
+        }
+    }
+
+    // All of the glyphs side by side, from code 0 on the left, with 1 where
+    // they're drawn and 0 where they aren't.
+    fn create_atlas(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> wgpu::TextureView {
+        let dim = Dimensions::new((ATLAS_GLYPHS * GLYPH_WIDTH) as u32,
+            GLYPH_HEIGHT as u32);
+        let width = dim.width() as usize;
+        let mut pixels = vec![0u8; dim.area()];
+        for code in 0..ATLAS_GLYPHS {
+            let bits = font::glyph(code as u8 as char);
+            for (y, &bits) in bits.iter().enumerate() {
+                for x in 0..GLYPH_WIDTH {
+                    if bits & (0x80 >> x) != 0 {
+                        pixels[y * width + code * GLYPH_WIDTH + x] = 255;
+                    }
+                }
+            }
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("glyph atlas"),
+            size: texture_size(dim),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+        });
+        write_texture(queue, &texture, dim, &pixels);
+        texture.create_view(&wgpu::TextureViewDescriptor::default())
+    }
+
+    // Change the text.  Nothing is drawn if there are no lines.
+    pub fn set_text(
+        &mut self,
+        lines: Vec<String>,
+    ) {
+        if lines != self.lines {
+            self.lines = lines;
+            self.dirty = true;
+        }
+    }
+
+    // The size of the text and its background, in pixels, before scaling.
+    pub fn dim(&self) -> Dimensions {
+        let cols = self.lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        Dimensions::new(
+            (cols * GLYPH_WIDTH + 2 * MARGIN) as u32,
+            (self.lines.len() * GLYPH_HEIGHT + 2 * MARGIN) as u32,
+        )
+    }
+
+    // How many characters there are across and down.
+    fn cell_dim(&self) -> Dimensions {
+        let cols = self.lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        Dimensions::new(cols.max(1) as u32, self.lines.len().max(1) as u32)
+    }
+
+    // Upload the text to the GPU, if it's changed.  The texture it goes in
+    // is only made again when the text has outgrown it.
+    pub fn update(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        if self.lines.is_empty() {
+            return;
+        }
+
+        let dim = self.cell_dim();
+        let fits = self.cells.as_ref().map_or(false, |cells| {
+            cells.dim.width() >= dim.width() && cells.dim.height() >= dim.height()
+        });
+        if !fits {
+            // Keep the room there was, so that text that changes size
+            // doesn't keep making it again.
+            let room = self.cells.as_ref().map_or(dim, |cells| Dimensions::new(
+                cells.dim.width().max(dim.width()),
+                cells.dim.height().max(dim.height())));
+            self.create_cells(device, room);
+        }
+
+        let width = dim.width() as usize;
+        let mut codes = vec![b' '; dim.area()];
+        for (row, line) in self.lines.iter().enumerate() {
+            for (col, c) in line.chars().enumerate() {
+                codes[row * width + col] = if c.is_ascii() { c as u8 } else { b'?' };
+            }
+        }
+        let cells = self.cells.as_ref().unwrap();
+        write_texture(queue, &cells.texture, dim, &codes);
+    }
+
+    fn create_cells(
+        &mut self,
+        device: &wgpu::Device,
+        dim: Dimensions,
+    ) {
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("text"),
+            size: texture_size(dim),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Uint,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_DST,
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        self.bind_group = Some(device.create_bind_group(
+            &wgpu::BindGroupDescriptor {
+                label: Some("text bind group"),
+                layout: &self.layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: self.params.buf().as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::TextureView(&view),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: wgpu::BindingResource::TextureView(&self.atlas),
+                    },
+                ],
+            }));
+        self.cells = Some(Cells {
+            texture,
+            dim,
+        });
+    }
+
+    // Draw the text over what's already in the view, with its top left
+    // corner at the given position in pixels, magnified by the scale.
+    pub fn render(
+        &self,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        window: Dimensions,
+        origin: (u32, u32),
+        scale: u32,
+    ) {
+        let bind_group = match &self.bind_group {
+            Some(bind_group) if !self.lines.is_empty() => bind_group,
+            _ => return,
+        };
+
+        let dim = self.dim();
+        let params = TextParams {
+            origin: [origin.0 as f32, origin.1 as f32],
+            size: [(dim.width() * scale) as f32, (dim.height() * scale) as f32],
+            window: [window.width() as f32, window.height() as f32],
+            text: [dim.width() as f32, dim.height() as f32],
+            foreground: FOREGROUND,
+            background: BACKGROUND,
+            margin: MARGIN as f32,
+            _pad: [0.0; 3],
+        };
+        queue.write_buffer(self.params.buf(), 0, bytemuck::bytes_of(&params));
+
+        let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("text"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        rpass.set_pipeline(&self.pipeline);
+        rpass.set_bind_group(0, bind_group, &[]);
+        rpass.draw(0..4, 0..1);
+    }
+}
@@ -0,0 +1,57 @@
+// Draws a block of text over the frame, a glyph from the atlas for each
+// character code in the text.
+
+[[block]]
+struct TextParams {
+    origin: vec2<f32>;      // top left corner, in pixels
+    size: vec2<f32>;        // in pixels, after scaling
+    window: vec2<f32>;
+    text: vec2<f32>;        // in pixels, before scaling, with the margins
+    foreground: vec4<f32>;
+    background: vec4<f32>;
+    margin: f32;            // in pixels, before scaling
+};
+
+struct VertexOutput {
+    [[builtin(position)]] position: vec4<f32>;
+    [[location(0)]] uv: vec2<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: TextParams;
+[[group(0), binding(1)]] var text: texture_2d<u32>;
+[[group(0), binding(2)]] var atlas: texture_2d<f32>;
+
+// The quad is drawn as a triangle strip, without any vertex buffer.
+[[stage(vertex)]]
+fn vs_main([[builtin(vertex_index)]] index: u32) -> VertexOutput {
+    let corner: vec2<f32> = vec2<f32>(f32(index & 1u32), f32((index >> 1u32) & 1u32));
+    let pixel: vec2<f32> = params.origin + corner * params.size;
+
+    var out: VertexOutput;
+    out.position = vec4<f32>(
+        pixel.x / params.window.x * 2.0 - 1.0,
+        1.0 - pixel.y / params.window.y * 2.0,
+        0.0, 1.0);
+    out.uv = corner;
+    return out;
+}
+
+[[stage(fragment)]]
+fn fs_main(in: VertexOutput) -> [[location(0)]] vec4<f32> {
+    // Where this is in the text, without the margin around it.
+    let margin: i32 = i32(params.margin);
+    let pixel: vec2<i32> = vec2<i32>(in.uv * params.text) - vec2<i32>(margin, margin);
+    let inside: vec2<i32> = vec2<i32>(params.text) - vec2<i32>(2 * margin, 2 * margin);
+    if (pixel.x < 0 || pixel.y < 0 || pixel.x >= inside.x || pixel.y >= inside.y) {
+        return params.background;
+    }
+
+    // This must match GLYPH_WIDTH and GLYPH_HEIGHT in font.rs
+    let glyph: vec2<i32> = vec2<i32>(8, 16);
+    let code: u32 = textureLoad(text, pixel / glyph, 0).r;
+    let texel: vec2<i32> = vec2<i32>(i32(code) * glyph.x, 0) + pixel % glyph;
+    if (textureLoad(atlas, texel, 0).r > 0.5) {
+        return params.foreground;
+    }
+    return params.background;
+}
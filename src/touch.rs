@@ -0,0 +1,209 @@
+// Turning touches into mouse input.
+//
+// Touch screens get mapped onto the same Example hooks as the mouse, so
+// the program doesn't need to know which it's getting:
+//
+// - one finger draws, like dragging with the left button;
+// - holding one finger still for a moment is a right click, for whatever
+//   that does, and dragging after that is a right button drag;
+// - two fingers pan, like dragging with the middle button;
+// - pinching zooms, like a touchpad scrolling.
+
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, ModifiersState, MouseButton, MouseScrollDelta,
+        Touch, TouchPhase},
+};
+
+use crate::window::{self, Example};
+
+// How long a finger has to stay put to count as a long press, in seconds.
+const LONG_PRESS: f64 = 0.5;
+
+// How far a finger can wander before it's moving, in pixels.
+const SLOP: f64 = 10.0;
+
+#[derive(Clone, Copy, Debug)]
+enum Gesture {
+    None,
+    // One finger is down, but it hasn't yet done enough to say what it's for.
+    Pending { start: (f64, f64), since: f64 },
+    // One finger is holding the given button down.
+    Drag(MouseButton),
+    // Two fingers, this far apart, are panning and zooming.
+    Pinch(f64),
+    // The gesture is over but some fingers are still down.
+    Ignore,
+}
+
+pub struct Touches {
+    fingers: Vec<(u64, (f64, f64))>,
+    gesture: Gesture,
+}
+
+fn distance(
+    a: (f64, f64),
+    b: (f64, f64),
+) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+fn midpoint(
+    a: (f64, f64),
+    b: (f64, f64),
+) -> (f64, f64) {
+    ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+}
+
+fn press<E: Example>(
+    example: &mut E,
+    button: MouseButton,
+) {
+    example.mouse_press(ElementState::Pressed, button, ModifiersState::empty());
+}
+
+fn release<E: Example>(
+    example: &mut E,
+    button: MouseButton,
+) {
+    example.mouse_press(ElementState::Released, button, ModifiersState::empty());
+}
+
+impl Touches {
+    pub fn new() -> Self {
+        Touches {
+            fingers: Vec::new(),
+            gesture: Gesture::None,
+        }
+    }
+
+    pub fn touch<E: Example>(
+        &mut self,
+        example: &mut E,
+        touch: Touch,
+    ) {
+        let pos = (touch.location.x, touch.location.y);
+        match touch.phase {
+            TouchPhase::Started => {
+                self.fingers.push((touch.id, pos));
+                self.started(example);
+            },
+            TouchPhase::Moved => {
+                if let Some(finger) = self.fingers.iter_mut()
+                    .find(|(id, _)| *id == touch.id) {
+                    finger.1 = pos;
+                    self.moved(example);
+                }
+            },
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if let Some(i) = self.fingers.iter()
+                    .position(|(id, _)| *id == touch.id) {
+                    self.fingers.remove(i);
+                    self.ended(example, touch.phase == TouchPhase::Ended);
+                }
+            },
+        }
+    }
+
+    // Called every frame, since a long press is when nothing happens.
+    pub fn tick<E: Example>(
+        &mut self,
+        example: &mut E,
+    ) {
+        if let Gesture::Pending { since, .. } = self.gesture {
+            if window::now() - since >= LONG_PRESS {
+                press(example, MouseButton::Right);
+                self.gesture = Gesture::Drag(MouseButton::Right);
+            }
+        }
+    }
+
+    fn started<E: Example>(
+        &mut self,
+        example: &mut E,
+    ) {
+        match (self.gesture, self.fingers.as_slice()) {
+            (Gesture::None, &[(_, pos)]) => {
+                example.mouse_move(pos.0, pos.1);
+                self.gesture = Gesture::Pending { start: pos, since: window::now() };
+            },
+
+            // A second finger turns whatever the first was doing into a pan.
+            (Gesture::Pending { .. }, &[(_, a), (_, b)])
+            | (Gesture::Drag(_), &[(_, a), (_, b)]) => {
+                if let Gesture::Drag(button) = self.gesture {
+                    release(example, button);
+                }
+                let (x, y) = midpoint(a, b);
+                example.mouse_move(x, y);
+                press(example, MouseButton::Middle);
+                self.gesture = Gesture::Pinch(distance(a, b));
+            },
+
+            // Any more fingers than that don't do anything.
+            (Gesture::Pinch(_), _) => {},
+            _ => self.stop(example),
+        }
+    }
+
+    fn moved<E: Example>(
+        &mut self,
+        example: &mut E,
+    ) {
+        match (self.gesture, self.fingers.as_slice()) {
+            (Gesture::Pending { start, .. }, &[(_, pos)]) => {
+                if distance(start, pos) > SLOP {
+                    press(example, MouseButton::Left);
+                    example.mouse_move(pos.0, pos.1);
+                    self.gesture = Gesture::Drag(MouseButton::Left);
+                }
+            },
+            (Gesture::Drag(_), &[(_, pos)]) => {
+                example.mouse_move(pos.0, pos.1);
+            },
+            (Gesture::Pinch(apart), &[(_, a), (_, b), ..]) => {
+                let (x, y) = midpoint(a, b);
+                example.mouse_move(x, y);
+                let now_apart = distance(a, b);
+                example.mouse_scroll(MouseScrollDelta::PixelDelta(
+                    PhysicalPosition::new(0.0, now_apart - apart)),
+                    ModifiersState::empty());
+                self.gesture = Gesture::Pinch(now_apart);
+            },
+            _ => {},
+        }
+    }
+
+    fn ended<E: Example>(
+        &mut self,
+        example: &mut E,
+        lifted: bool,
+    ) {
+        match self.gesture {
+            // A quick tap is a click.
+            Gesture::Pending { .. } if lifted => {
+                press(example, MouseButton::Left);
+                release(example, MouseButton::Left);
+                self.gesture = Gesture::None;
+            },
+            _ => self.stop(example),
+        }
+    }
+
+    // Finish off the gesture, and ignore the fingers that are left.
+    fn stop<E: Example>(
+        &mut self,
+        example: &mut E,
+    ) {
+        match self.gesture {
+            Gesture::Drag(button) => release(example, button),
+            Gesture::Pinch(_) => release(example, MouseButton::Middle),
+            _ => {},
+        }
+        self.gesture = if self.fingers.is_empty() {
+            Gesture::None
+        } else {
+            Gesture::Ignore
+        };
+    }
+}
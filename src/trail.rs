@@ -0,0 +1,148 @@
+// Trails of where cells have been alive, fading away over time.
+//
+// Each step, an accumulation buffer decays by a factor and is brought back
+// up to full wherever a cell is alive, so gliders leave streaks behind them
+// and oscillators show the whole area they cover.  The result goes into a
+// texture that the renderer draws with its own colormap.
+
+use std::borrow::Cow;
+use bytemuck::{Pod, Zeroable};
+
+use crate::{
+    bindable::{Bindable, BindAccess, Binder, Buffer, Buffer2D, BufferType, Texture},
+    dimensions::Dimensions,
+};
+
+// ---------------------------------------------------------------------------
+// Data that is shared between Rust and the compute pipeline in WGSL.
+
+// This must match the value of the workgroup_size() annotation in trail.wgsl
+const WORKGROUP_SIZE: (u32, u32) = (8, 8);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Pod, Zeroable)]
+struct TrailParams {
+    decay: f32,
+    reset: u32,
+    _pad: [u32; 2],
+}
+
+// ---------------------------------------------------------------------------
+
+pub struct Trail {
+    shader: wgpu::ShaderModule,
+    params: Buffer,
+    accum: Buffer2D<f32>,
+    texture: Texture,
+    pipeline: wgpu::ComputePipeline,
+    bind_group: wgpu::BindGroup,
+    dim: Dimensions,
+    pub decay: f32,
+    reset: bool,            // the trail is left over from before
+}
+
+impl Trail {
+    pub fn new(
+        device: &wgpu::Device,
+        dim: Dimensions,
+        life_params: &dyn Bindable,
+        cells: &dyn Bindable,
+        decay: f32,
+    ) -> Self {
+        let shader = device.create_shader_module(
+            &wgpu::ShaderModuleDescriptor {
+                label: Some("trail"),
+                source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
+                    include_str!("trail.wgsl"))),
+            });
+        let params = Buffer::new(device, "trail parameters",
+            BufferType::Uniform, std::mem::size_of::<TrailParams>());
+        let accum = Buffer2D::new(device, "trail", dim);
+        let texture = Texture::new(device, dim, wgpu::TextureFormat::R32Float);
+        let (pipeline, bind_group) = Trail::bind_up(device, &shader,
+            life_params, &params, cells, &accum, &texture);
+
+        Trail {
+            shader,
+            params,
+            accum,
+            texture,
+            pipeline,
+            bind_group,
+            dim,
+            decay,
+            reset: true,
+        }
+    }
+
+    fn bind_up(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        life_params: &dyn Bindable,
+        params: &Buffer,
+        cells: &dyn Bindable,
+        accum: &Buffer2D<f32>,
+        texture: &Texture,
+    ) -> (wgpu::ComputePipeline, wgpu::BindGroup) {
+        let args: Vec<(_, &dyn Bindable)> = vec![
+            (BindAccess::ReadOnly,    life_params),
+            (BindAccess::ReadOnly,    params),
+            (BindAccess::ReadSampled, cells),
+            (BindAccess::WriteOnly,   accum),
+            (BindAccess::WriteOnly,   texture),
+        ];
+        Binder::bind_up(device, shader, "main", &args)
+    }
+
+    // The grid has changed size; the trail starts again from nothing.
+    pub fn resize(
+        &mut self,
+        device: &wgpu::Device,
+        dim: Dimensions,
+        life_params: &dyn Bindable,
+        cells: &dyn Bindable,
+    ) {
+        self.accum = Buffer2D::new(device, "trail", dim);
+        self.texture = Texture::new(device, dim, wgpu::TextureFormat::R32Float);
+        let (pipeline, bind_group) = Trail::bind_up(device, &self.shader,
+            life_params, &self.params, cells, &self.accum, &self.texture);
+        self.pipeline = pipeline;
+        self.bind_group = bind_group;
+        self.dim = dim;
+    }
+
+    // Forget the trail, such as when it hasn't been kept up to date.
+    pub fn reset(&mut self) {
+        self.reset = true;
+    }
+
+    // Fade the trail and add the cells from the step that's just been
+    // encoded.
+    pub fn step(
+        &mut self,
+        queue: &wgpu::Queue,
+        command_encoder: &mut wgpu::CommandEncoder,
+    ) {
+        let params = TrailParams {
+            decay: self.decay,
+            reset: self.reset as u32,
+            _pad: [0; 2],
+        };
+        queue.write_buffer(self.params.buf(), 0, bytemuck::bytes_of(&params));
+        self.reset = false;
+
+        let xgroups = (self.dim.width() + WORKGROUP_SIZE.0 - 1) / WORKGROUP_SIZE.0;
+        let ygroups = (self.dim.height() + WORKGROUP_SIZE.1 - 1) / WORKGROUP_SIZE.1;
+        let mut cpass = command_encoder.begin_compute_pass(
+            &wgpu::ComputePassDescriptor {
+                label: Some("trail")
+            });
+        cpass.set_pipeline(&self.pipeline);
+        cpass.set_bind_group(0, &self.bind_group, &[]);
+        cpass.dispatch(xgroups, ygroups, 1);
+    }
+
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+}
@@ -0,0 +1,52 @@
+// Fades the trail a little, and brings it back up wherever cells are alive.
+
+[[block]]
+struct LifeParams {
+    width : u32;
+    height : u32;
+    threshold : f32;
+    birth : u32;
+    survive : u32;
+};
+
+[[block]]
+struct TrailParams {
+    decay : f32;        // how much of the trail is left after each step
+    reset : u32;        // whether to start again from nothing
+    pad0 : u32;
+    pad1 : u32;
+};
+
+[[block]]
+struct Cells {
+    cells : array<f32>;
+};
+
+[[group(0), binding(0)]] var<uniform> params: LifeParams;
+[[group(0), binding(1)]] var<uniform> trail_params: TrailParams;
+[[group(0), binding(2)]] var cells: texture_2d<f32>;
+[[group(0), binding(3)]] var<storage, read_write> accum: Cells;
+[[group(0), binding(4)]] var trail: texture_storage_2d<r32float, write>;
+
+[[stage(compute), workgroup_size(8, 8)]]
+fn main([[builtin(global_invocation_id)]] global_id: vec3<u32>) {
+    let X : u32 = global_id.x;
+    let Y : u32 = global_id.y;
+    if (X >= params.width || Y >= params.height) {
+        return;
+    }
+    let pix : u32 = Y * params.width + X;
+    let coord : vec2<i32> = vec2<i32>(i32(X), i32(Y));
+
+    var level : f32 = accum.cells[pix] * trail_params.decay;
+    if (trail_params.reset != 0u32) {
+        level = 0.0;
+    }
+    let value : vec4<f32> = textureLoad(cells, coord, 0);
+    if (value.x > params.threshold) {
+        level = 1.0;
+    }
+
+    accum.cells[pix] = level;
+    textureStore(trail, coord, vec4<f32>(level, 0.0, 0.0, 1.0));
+}
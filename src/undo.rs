@@ -0,0 +1,212 @@
+// Undoing and redoing edits to the Life grid.
+//
+// An edit is kept as the rectangles of cells that it touched, with their
+// values from just before and just after it, rather than as a copy of the
+// whole grid.  A brush stroke is one edit, made up of a rectangle for each
+// frame it was painted in.  The oldest edits are forgotten once they all
+// take up too much memory.
+//
+// So as not to hold up painting, a stroke's rectangles are read back from
+// the GPU without waiting, and are only added to the edit once they've
+// arrived.
+
+use std::collections::VecDeque;
+use std::mem;
+
+use crate::{
+    bindable::Readback,
+    dimensions::Rect,
+};
+
+struct Patch {
+    rect: Rect,
+    before: Vec<f32>,
+    after: Vec<f32>,
+}
+
+impl Patch {
+    fn bytes(&self) -> usize {
+        (self.before.len() + self.after.len()) * mem::size_of::<f32>()
+    }
+}
+
+// A patch whose cells are still on their way back from the GPU.
+struct PendingPatch {
+    rect: Rect,
+    before: Readback<f32>,
+    after: Readback<f32>,
+    last: bool,             // whether its edit is over
+}
+
+type Edit = Vec<Patch>;
+
+fn edit_bytes(edit: &[Patch]) -> usize {
+    edit.iter().map(Patch::bytes).sum()
+}
+
+pub struct History {
+    done: VecDeque<Edit>,
+    undone: Vec<Edit>,
+    open: Option<Edit>,     // an edit that's still happening, like a stroke
+    pending: VecDeque<PendingPatch>,    // to add to the open edit, in order
+    bytes: usize,           // used by everything in done and undone
+    max_bytes: usize,
+}
+
+impl History {
+    pub fn new(
+        max_bytes: usize,
+    ) -> Self {
+        History {
+            done: VecDeque::new(),
+            undone: Vec::new(),
+            open: None,
+            pending: VecDeque::new(),
+            bytes: 0,
+            max_bytes,
+        }
+    }
+
+    // Forget everything, such as when the grid changes size and the
+    // edits no longer fit it.
+    pub fn clear(&mut self) {
+        *self = History::new(self.max_bytes);
+    }
+
+    // Add a changed rectangle to the edit that's happening,
+    // starting a new one if need be.
+    pub fn record(
+        &mut self,
+        rect: Rect,
+        before: Vec<f32>,
+        after: Vec<f32>,
+    ) {
+        self.open.get_or_insert_with(Vec::new).push(Patch {
+            rect,
+            before,
+            after,
+        });
+    }
+
+    // Add a changed rectangle to the edit that's happening, from cells that
+    // are still being read back.  The readbacks should already be mapping.
+    pub fn record_pending(
+        &mut self,
+        rect: Rect,
+        before: Readback<f32>,
+        after: Readback<f32>,
+    ) {
+        self.pending.push_back(PendingPatch {
+            rect,
+            before,
+            after,
+            last: false,
+        });
+    }
+
+    fn record_arrived(
+        &mut self,
+        patch: PendingPatch,
+        before: Vec<f32>,
+        after: Vec<f32>,
+    ) {
+        self.record(patch.rect, before, after);
+        if patch.last {
+            self.close();
+        }
+    }
+
+    // Record the pending rectangles whose cells have arrived, without
+    // waiting for the rest.  The device needs polling for them to arrive.
+    pub fn receive(&mut self) {
+        while let Some(patch) = self.pending.front_mut() {
+            if !(patch.before.is_ready() && patch.after.is_ready()) {
+                break;
+            }
+            let mut patch = self.pending.pop_front().unwrap();
+            let before = patch.before.take();
+            let after = patch.after.take();
+            self.record_arrived(patch, before, after);
+        }
+    }
+
+    // Wait for all of the pending rectangles, so that the history is up to
+    // date, such as before undoing.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn wait(
+        &mut self,
+        device: &wgpu::Device,
+    ) {
+        while let Some(mut patch) = self.pending.pop_front() {
+            let before = patch.before.wait(device);
+            let after = patch.after.wait(device);
+            self.record_arrived(patch, before, after);
+        }
+    }
+
+    // The edit that's happening is over.  It can now be undone, and
+    // anything that was undone before it can't be redone any more.
+    // If some of it is still being read back, that happens once it's
+    // arrived.
+    pub fn finish(&mut self) {
+        match self.pending.back_mut() {
+            Some(patch) => patch.last = true,
+            None => self.close(),
+        }
+    }
+
+    fn close(&mut self) {
+        let edit = match self.open.take() {
+            Some(edit) => edit,
+            None => return,
+        };
+
+        let bytes = edit_bytes(&edit);
+        if bytes > self.max_bytes {
+            log::warn!("undo: that edit is too big to undo");
+            return;
+        }
+
+        for e in self.undone.drain(..) {
+            self.bytes -= edit_bytes(&e);
+        }
+        self.done.push_back(edit);
+        self.bytes += bytes;
+
+        while self.bytes > self.max_bytes {
+            let oldest = self.done.pop_front().unwrap();
+            self.bytes -= edit_bytes(&oldest);
+        }
+    }
+
+    // Take back the most recent edit.  Returns the rectangles to restore
+    // and what to put in them, in the order to do it.
+    pub fn undo(&mut self) -> Vec<(Rect, &[f32])> {
+        self.finish();
+        match self.done.pop_back() {
+            Some(edit) => self.undone.push(edit),
+            None => {
+                log::info!("undo: nothing to undo");
+                return Vec::new();
+            }
+        }
+        self.undone.last().unwrap().iter().rev()
+            .map(|p| (p.rect, p.before.as_slice()))
+            .collect()
+    }
+
+    // Put back the most recently undone edit.
+    pub fn redo(&mut self) -> Vec<(Rect, &[f32])> {
+        self.finish();
+        match self.undone.pop() {
+            Some(edit) => self.done.push_back(edit),
+            None => {
+                log::info!("undo: nothing to redo");
+                return Vec::new();
+            }
+        }
+        self.done.back().unwrap().iter()
+            .map(|p| (p.rect, p.after.as_slice()))
+            .collect()
+    }
+}
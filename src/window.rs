@@ -2,15 +2,20 @@
 // based on https://github.com/gfx-rs/wgpu-rs/blob/master/examples/framework.rs
 //   (licensed under https://choosealicense.com/licenses/mpl-2.0/)
 
+use std::convert::TryFrom;
 use std::future::Future;
 use std::rc::Rc;
+use std::str::FromStr;
 use cfg_if::cfg_if;
+use serde::Deserialize;
 
 use winit::{
-    event::{self, WindowEvent},
+    event::{self, ModifiersState, MouseScrollDelta, WindowEvent},
     event_loop::{ControlFlow, EventLoop},
 };
 
+use crate::touch::Touches;
+
 #[cfg(not(target_arch = "wasm32"))]
 use {
     std::time::{Duration, Instant},
@@ -22,12 +27,119 @@ use {
     wasm_bindgen::{prelude::*, closure::Closure, JsCast, JsValue},
 };
 
+// The time in seconds, from some arbitrary starting point.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+    SystemTime::now().duration_since(UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64())
+}
+
+// (std::time doesn't work under wasm32.)
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+    js_sys::Date::now() / 1000.0
+}
+
 pub enum WindowOps {
     Quit,
     FullScreen,
     UnFullScreen,
 }
 
+// How frames are handed over to the display.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PresentMode {
+    Fifo,       // wait for vsync
+    Mailbox,    // replace any frame that's waiting for vsync
+    Immediate,  // don't wait, and maybe tear
+}
+
+impl FromStr for PresentMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fifo" => Ok(PresentMode::Fifo),
+            "mailbox" => Ok(PresentMode::Mailbox),
+            "immediate" => Ok(PresentMode::Immediate),
+            _ => Err(format!("unknown present mode \"{}\"", s)),
+        }
+    }
+}
+
+impl From<PresentMode> for wgpu::PresentMode {
+    fn from(mode: PresentMode) -> Self {
+        match mode {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+// How often to draw a frame.  In the config file, this is a number of
+// frames per second, or "monitor" or "uncapped".
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(try_from = "FrameRateSetting")]
+pub enum FrameRate {
+    Monitor,        // the monitor's refresh rate, if it can be found
+    Fps(f64),
+    Uncapped,       // as fast as frames can be presented
+}
+
+// The slowest frame rate there can be.
+const MIN_FPS: f64 = 0.1;
+
+impl Default for FrameRate {
+    fn default() -> Self {
+        FrameRate::Monitor
+    }
+}
+
+impl FromStr for FrameRate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "monitor" => Ok(FrameRate::Monitor),
+            "uncapped" => Ok(FrameRate::Uncapped),
+            // A tiny rate would make for a frame time too long to hold.
+            _ => match s.parse::<f64>() {
+                Ok(fps) if fps > 0.0 => Ok(FrameRate::Fps(fps.max(MIN_FPS))),
+                _ => Err(format!(
+                    "frame rate \"{}\" isn't a number, \"monitor\" or \"uncapped\"", s)),
+            },
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FrameRateSetting {
+    Number(f64),
+    Name(String),
+}
+
+impl TryFrom<FrameRateSetting> for FrameRate {
+    type Error = String;
+
+    fn try_from(setting: FrameRateSetting) -> Result<Self, Self::Error> {
+        match setting {
+            FrameRateSetting::Number(fps) => fps.to_string().parse(),
+            FrameRateSetting::Name(name) => name.parse(),
+        }
+    }
+}
+
+// How the example wants its frames shown.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Display {
+    pub present_mode: Option<PresentMode>,  // None means Mailbox, if it can
+    pub frame_rate: FrameRate,
+}
+
 pub trait Example: 'static + Sized {
     fn optional_features() -> wgpu::Features {
         wgpu::Features::empty()
@@ -53,21 +165,36 @@ pub trait Example: 'static + Sized {
     fn key_press(
         &mut self,
         keycode: winit::event::VirtualKeyCode,
+        modifiers: ModifiersState,
     ) -> Option<WindowOps>;
+    fn key_release(
+        &mut self,
+        _keycode: winit::event::VirtualKeyCode,
+        _modifiers: ModifiersState,
+    ) {
+    }
     fn mouse_press(
         &mut self,
         state: winit::event::ElementState,
         button: winit::event::MouseButton,
+        modifiers: ModifiersState,
     );
     fn mouse_move(
         &mut self,
         x: f64,
         y: f64,
     );
+    fn mouse_scroll(
+        &mut self,
+        _delta: MouseScrollDelta,
+        _modifiers: ModifiersState,
+    ) {
+    }
     fn update(
         &mut self,
-        event: WindowEvent,
-    );
+        _event: WindowEvent,
+    ) {
+    }
     fn render(
         &mut self,
         frame: &wgpu::TextureView,
@@ -75,6 +202,15 @@ pub trait Example: 'static + Sized {
         queue: &wgpu::Queue,
         spawner: &Spawner,
     );
+    fn quit(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+    ) {
+    }
+    fn display(&self) -> Display {
+        Display::default()
+    }
 }
 
 struct Setup {
@@ -151,6 +287,10 @@ async fn setup<E: Example>() -> Setup {
             // eliminate it, I can at least make it black.
             body.style().set_property("background-color", "black").unwrap();
 
+            // Touches on the canvas are for us, not for scrolling or
+            // zooming the page.
+            window.canvas().style().set_property("touch-action", "none").unwrap();
+
             // Allow the winit window to be accessed from callbacks.
             let window = Rc::new(window);
 
@@ -223,6 +363,24 @@ async fn setup<E: Example>() -> Setup {
         log::info!("Using {} ({:?})", adapter_info.name, adapter_info.backend);
     }
 
+    let (device, queue) = request_device::<E>(&adapter).await;
+
+    Setup {
+        window,
+        event_loop,
+        instance,
+        size,
+        surface,
+        adapter,
+        device,
+        queue,
+    }
+}
+
+// Get a device from the adapter, with <E>'s features and limits.
+pub async fn request_device<E: Example>(
+    adapter: &wgpu::Adapter,
+) -> (wgpu::Device, wgpu::Queue) {
     let optional_features = E::optional_features();
     let required_features = E::required_features();
     let adapter_features = adapter.features();
@@ -235,7 +393,7 @@ async fn setup<E: Example>() -> Setup {
     let needed_limits = E::required_limits();
 
     let trace_dir = std::env::var("WGPU_TRACE");
-    let (device, queue) = adapter
+    adapter
         .request_device(
             &wgpu::DeviceDescriptor {
                 label: None,
@@ -245,18 +403,7 @@ async fn setup<E: Example>() -> Setup {
             trace_dir.ok().as_ref().map(std::path::Path::new),
         )
         .await
-        .expect("Unable to find a suitable GPU adapter!");
-
-    Setup {
-        window,
-        event_loop,
-        instance,
-        size,
-        surface,
-        adapter,
-        device,
-        queue,
-    }
+        .expect("Unable to find a suitable GPU adapter!")
 }
 
 // Initialize the rest of the code, and run the event loop.
@@ -291,7 +438,17 @@ fn start<E: Example>(
         log::info!("Performing no-op resize (non-wasm32)...");
         example.resize(&config, &device, &queue);
     }
+
+    // The example has read its settings by now, so it can say how
+    // it wants frames shown.
+    let display = example.display();
+    config.present_mode = present_mode(display.present_mode);
+    // wgpu can't say which modes the surface supports, and it falls
+    // back to Fifo (with a warning of its own) if this one isn't.
+    log::info!("Present mode {:?} requested", config.present_mode);
     surface.configure(&device, &config);
+    #[cfg(not(target_arch = "wasm32"))]
+    let target_frametime = frame_time(&window, display.frame_rate);
 
     #[cfg(not(target_arch = "wasm32"))]
     let mut last_update_inst = Instant::now();
@@ -300,6 +457,12 @@ fn start<E: Example>(
     #[cfg(not(target_arch = "wasm32"))]
     let (mut frame_count, mut accum_time) = (0, 0.0);
 
+    // The state of the shift, control, alt and logo keys.
+    let mut modifiers = ModifiersState::empty();
+
+    // The fingers on a touch screen, which stand in for the mouse.
+    let mut touches = Touches::new();
+
     log::info!("Entering render loop...");
     event_loop.run(move |event, _, control_flow| {
         let _ = (&instance, &adapter); // force ownership by the closure
@@ -313,23 +476,25 @@ fn start<E: Example>(
             // fires after all RedrawRequested events have been processed,
             // and control flow is about to be taken away
             event::Event::RedrawEventsCleared => {
+                touches.tick(&mut example);
+
                 #[cfg(not(target_arch = "wasm32"))]
                 {
-                    // Clamp to some max framerate to avoid busy-looping too much
+                    // Clamp to the target framerate to avoid busy-looping too much
                     // (we might be in wgpu::PresentMode::Mailbox, thus discarding superfluous frames)
-                    //
-                    // winit has window.current_monitor().video_modes() but that is a list of all full screen video modes.
-                    // So without extra dependencies it's a bit tricky to get the max refresh rate we can run the window on.
-                    // Therefore we just go with 60fps - sorry 120hz+ folks!
-                    let target_frametime = Duration::from_secs_f64(1.0 / 60.0);
-                    let time_since_last_frame = last_update_inst.elapsed();
-                    if time_since_last_frame >= target_frametime {
-                        window.request_redraw();
-                        last_update_inst = Instant::now();
-                    } else {
-                        *control_flow = ControlFlow::WaitUntil(
-                            Instant::now() + target_frametime - time_since_last_frame,
-                        );
+                    match target_frametime {
+                        None => window.request_redraw(),
+                        Some(target_frametime) => {
+                            let time_since_last_frame = last_update_inst.elapsed();
+                            if time_since_last_frame >= target_frametime {
+                                window.request_redraw();
+                                last_update_inst = Instant::now();
+                            } else {
+                                *control_flow = ControlFlow::WaitUntil(
+                                    Instant::now() + target_frametime - time_since_last_frame,
+                                );
+                            }
+                        },
                     }
 
                     spawner.run_until_stalled();
@@ -359,10 +524,10 @@ fn start<E: Example>(
                         virtual_keycode: Some(key),
                         state: event::ElementState::Pressed,
                         ..
-                        // It would be nice to be able to pass along e.g. the
-                        // state of the shift key here, but that's not kosher;
+                        // The modifiers field of KeyboardInput is deprecated;
                         // cf. https://github.com/rust-windowing/winit/issues/1824
-                    } => if let Some(op) = example.key_press(key) {
+                        // so they come from ModifiersChanged events instead.
+                    } => if let Some(op) = example.key_press(key, modifiers) {
                         match op {
                             WindowOps::Quit =>
                                 *control_flow = ControlFlow::Exit,
@@ -374,13 +539,28 @@ fn start<E: Example>(
                                 window.set_fullscreen(None),
                         }
                     },
-                    // don't care about "virtual_keycode = None" or "state = Released"
+                    event::KeyboardInput {
+                        virtual_keycode: Some(key),
+                        state: event::ElementState::Released,
+                        ..
+                    } => example.key_release(key, modifiers),
+                    // don't care about "virtual_keycode = None"
                     _ => (),
                 },
 
+                // fires when the shift, control, alt or logo keys change
+                WindowEvent::ModifiersChanged(state) => {
+                    modifiers = state;
+                },
+
                 // fires when receiving a mouse-down event
                 WindowEvent::MouseInput { state, button, .. } => {
-                    example.mouse_press(state, button);
+                    example.mouse_press(state, button, modifiers);
+                },
+
+                // fires when the scroll wheel or touchpad scrolls
+                WindowEvent::MouseWheel { delta, .. } => {
+                    example.mouse_scroll(delta, modifiers);
                 },
 
                 // fires when receiving a mouse-move event
@@ -390,6 +570,11 @@ fn start<E: Example>(
                     example.mouse_move(x, y);
                 },
 
+                // fires when a finger touches, moves or lifts
+                WindowEvent::Touch(touch) => {
+                    touches.touch(&mut example, touch);
+                },
+
                 WindowEvent::CloseRequested => {
                     *control_flow = ControlFlow::Exit;
                 }
@@ -410,7 +595,8 @@ fn start<E: Example>(
                     last_frame_inst = Instant::now();
                     frame_count += 1;
                     if frame_count == 100 {
-                        println!(
+                        // (stdout may be carrying a recorded video stream)
+                        eprintln!(
                             "Avg frame time {}ms",
                             accum_time * 1000.0 / frame_count as f32
                         );
@@ -435,11 +621,66 @@ fn start<E: Example>(
 
                 example.render(&view, &device, &queue, &spawner);
             }
+
+            // fires once, just before the event loop exits
+            event::Event::LoopDestroyed => {
+                example.quit(&device, &queue);
+            }
             _ => {}
         }
     });
 }
 
+// The present mode to ask for.  wgpu can't say which modes a surface
+// supports, but when it's given one that isn't, it falls back to Fifo
+// itself, with a warning.  Browsers only do Fifo.
+fn present_mode(
+    requested: Option<PresentMode>,
+) -> wgpu::PresentMode {
+    if cfg!(target_arch = "wasm32") {
+        return wgpu::PresentMode::Fifo;
+    }
+    requested.unwrap_or(PresentMode::Mailbox).into()
+}
+
+// How long to leave between frames, or None to not wait at all.
+#[cfg(not(target_arch = "wasm32"))]
+fn frame_time(
+    window: &winit::window::Window,
+    frame_rate: FrameRate,
+) -> Option<Duration> {
+    let fps = match frame_rate {
+        FrameRate::Uncapped => {
+            log::info!("Frame rate uncapped");
+            return None;
+        },
+        FrameRate::Fps(fps) => fps,
+        FrameRate::Monitor => monitor_refresh_rate(window).map_or_else(|| {
+            log::info!("Monitor refresh rate unknown; assuming 60Hz");
+            60.0
+        }, f64::from),
+    };
+    log::info!("Frame rate capped at {}fps", fps);
+    Some(Duration::from_secs_f64(1.0 / fps))
+}
+
+// The video modes are all of the monitor's full screen modes, so the ones
+// the same size as the monitor is now are the best guess at how it's
+// running; failing that, the fastest of any of them.
+#[cfg(not(target_arch = "wasm32"))]
+fn monitor_refresh_rate(
+    window: &winit::window::Window,
+) -> Option<u16> {
+    let monitor = window.current_monitor()?;
+    let size = monitor.size();
+    let fastest = |same_size: bool| monitor.video_modes()
+        .filter(|mode| !same_size || mode.size() == size)
+        .map(|mode| mode.refresh_rate())
+        .filter(|&rate| rate > 0)
+        .max();
+    fastest(true).or_else(|| fastest(false))
+}
+
 // An object that can spawn a task, in both wasm and native forms.
 #[cfg(not(target_arch = "wasm32"))]
 pub struct Spawner<'a> {
@@ -448,7 +689,7 @@ pub struct Spawner<'a> {
 
 #[cfg(not(target_arch = "wasm32"))]
 impl<'a> Spawner<'a> {
-    fn new() -> Self {
+    pub fn new() -> Self {
         Self {
             executor: async_executor::LocalExecutor::new(),
         }
@@ -459,7 +700,7 @@ impl<'a> Spawner<'a> {
         self.executor.spawn(future).detach();
     }
 
-    fn run_until_stalled(&self) {
+    pub fn run_until_stalled(&self) {
         while self.executor.try_tick() {}
     }
 }